@@ -0,0 +1,81 @@
+use ethercat_soem_sys as sys;
+use std::{mem, net::Ipv4Addr, os::raw::c_char};
+
+/// Length of the DNS name including the terminating zero
+const EOE_DNS_NAME_LENGTH: usize = 32;
+
+/// IP parameters of an EoE port
+///
+/// Only the given parameters are set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EoeIpParam {
+    pub mac: Option<[u8; 6]>,
+    pub ip: Option<Ipv4Addr>,
+    pub subnet: Option<Ipv4Addr>,
+    pub default_gateway: Option<Ipv4Addr>,
+    pub dns_ip: Option<Ipv4Addr>,
+    /// Names are truncated to 31 bytes.
+    pub dns_name: Option<String>,
+}
+
+impl EoeIpParam {
+    pub(crate) fn to_sys(&self) -> sys::eoe_param_t {
+        let mut param: sys::eoe_param_t = unsafe { mem::zeroed() };
+        if let Some(mac) = self.mac {
+            param.set_mac_set(1);
+            param.mac.addr = mac;
+        }
+        if let Some(ip) = self.ip {
+            param.set_ip_set(1);
+            param.ip.addr = ip_addr(ip);
+        }
+        if let Some(subnet) = self.subnet {
+            param.set_subnet_set(1);
+            param.subnet.addr = ip_addr(subnet);
+        }
+        if let Some(gateway) = self.default_gateway {
+            param.set_default_gateway_set(1);
+            param.default_gateway.addr = ip_addr(gateway);
+        }
+        if let Some(dns_ip) = self.dns_ip {
+            param.set_dns_ip_set(1);
+            param.dns_ip.addr = ip_addr(dns_ip);
+        }
+        if let Some(name) = &self.dns_name {
+            param.set_dns_name_set(1);
+            for (dst, src) in param
+                .dns_name
+                .iter_mut()
+                .zip(name.bytes().take(EOE_DNS_NAME_LENGTH - 1))
+            {
+                *dst = src as c_char;
+            }
+        }
+        param
+    }
+}
+
+/// SOEM stores the addresses in network byte order
+/// (like `EOE_IP4_ADDR_TO_U32`).
+fn ip_addr(ip: Ipv4Addr) -> u32 {
+    u32::from_ne_bytes(ip.octets())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_addresses_in_network_byte_order() {
+        let param = EoeIpParam {
+            ip: Some(Ipv4Addr::new(192, 168, 1, 2)),
+            subnet: Some(Ipv4Addr::new(255, 255, 255, 0)),
+            ..Default::default()
+        }
+        .to_sys();
+        assert_eq!(param.ip_set(), 1);
+        assert_eq!(param.ip.addr.to_ne_bytes(), [192, 168, 1, 2]);
+        assert_eq!(param.subnet.addr.to_ne_bytes(), [255, 255, 255, 0]);
+        assert_eq!(param.default_gateway_set(), 0);
+    }
+}
@@ -5,10 +5,25 @@ use thiserror::Error;
 #[derive(Debug, Clone, PartialEq)]
 pub struct Error {
     pub err_type: ErrType,
+    /// Slave number (`0` = master)
+    pub slave: u16,
+    pub index: u16,
+    pub sub_idx: u8,
     pub abort_code: i32,
+    /// Content of an emergency message (see [`ErrType::Emergency`])
+    pub emergency: Option<EmergencyCode>,
     pub msg: String,
 }
 
+/// Content of a CoE emergency message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmergencyCode {
+    pub error_code: u16,
+    pub error_register: u8,
+    /// Manufacturer specific data
+    pub data: [u8; 5],
+}
+
 /// SOEM context error type
 #[derive(Debug, Clone, Copy, PartialEq, Error)]
 pub enum ErrType {
@@ -62,10 +77,26 @@ impl From<sys::ec_errort> for Error {
     fn from(e: sys::ec_errort) -> Self {
         let err_type = ErrType::from(e.Etype);
         let abort_code = unsafe { e.__bindgen_anon_1.AbortCode };
+        let emergency = if err_type == ErrType::Emergency {
+            let code = unsafe { e.__bindgen_anon_1.__bindgen_anon_1 };
+            let [w1_lo, w1_hi] = code.w1.to_le_bytes();
+            let [w2_lo, w2_hi] = code.w2.to_le_bytes();
+            Some(EmergencyCode {
+                error_code: code.ErrorCode,
+                error_register: code.ErrorReg,
+                data: [code.b1, w1_lo, w1_hi, w2_lo, w2_hi],
+            })
+        } else {
+            None
+        };
         let msg = unsafe { super::c_array_to_string(sys::ecx_err2string(e)) };
         Error {
             err_type,
+            slave: e.Slave,
+            index: e.Index,
+            sub_idx: e.SubIdx,
             abort_code,
+            emergency,
             msg,
         }
     }
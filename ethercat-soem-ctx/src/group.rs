@@ -1,5 +1,5 @@
 use ethercat_soem_sys as sys;
-use std::{fmt, mem};
+use std::{fmt, mem, slice};
 
 /// SOEM `ec_group` wrapper
 #[repr(C)]
@@ -12,25 +12,42 @@ impl Default for Group {
 }
 
 impl Group {
-    // TODO:
-    // logical start address for this group
-    //pub logstartaddr: uint32,
-
-    // TODO:
-    // output bytes, if Obits < 8 then Obytes = 0
-    //pub Obytes: uint32,
+    /// Logical start address for this group
+    pub const fn log_start_addr(&self) -> u32 {
+        self.0.logstartaddr
+    }
+    /// Output bytes
+    pub const fn output_bytes(&self) -> u32 {
+        self.0.Obytes
+    }
 
-    // TODO:
-    // output pointer in IOmap buffer
-    //pub outputs: *mut uint8,
+    /// Outputs of the group in the I/O map
+    pub fn outputs(&self) -> &[u8] {
+        if self.0.outputs.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.0.outputs, self.output_bytes() as usize) }
+    }
+    /// Mutable access to the outputs of the group
+    pub fn outputs_mut(&mut self) -> &mut [u8] {
+        if self.0.outputs.is_null() {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(self.0.outputs, self.output_bytes() as usize) }
+    }
 
-    // TODO:
-    // input bytes, if Ibits < 8 then Ibytes = 0
-    //pub Ibytes: uint32,
+    /// Input bytes
+    pub const fn input_bytes(&self) -> u32 {
+        self.0.Ibytes
+    }
 
-    // TODO:
-    // input pointer in IOmap buffer
-    //pub inputs: *mut uint8,
+    /// Inputs of the group in the I/O map
+    pub fn inputs(&self) -> &[u8] {
+        if self.0.inputs.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.0.inputs, self.input_bytes() as usize) }
+    }
 
     // TODO:
     // has DC capability
@@ -69,9 +86,14 @@ impl Group {
         self.0.inputsWKC
     }
 
-    // TODO:
-    // check slave states
-    // pub docheckstate: boolean,
+    /// Check slave states
+    pub const fn check_state(&self) -> bool {
+        self.0.docheckstate != 0
+    }
+    /// Set or reset the flag to check the slave states
+    pub fn set_check_state(&mut self, check: bool) {
+        self.0.docheckstate = if check { 1 } else { 0 };
+    }
 
     // TODO:
     // IO segmentation list. Datagrams must not break SM in two.
@@ -82,8 +104,12 @@ impl fmt::Debug for Group {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // TODO: add missing fields
         f.debug_struct("Group")
+            .field("log_start_addr", &self.log_start_addr())
+            .field("output_bytes", &self.output_bytes())
+            .field("input_bytes", &self.input_bytes())
             .field("outputs_wkc", &self.outputs_wkc())
             .field("inputs_wkc", &self.inputs_wkc())
+            .field("check_state", &self.check_state())
             .finish()
     }
 }
@@ -0,0 +1,206 @@
+use ethercat_soem_sys as sys;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    os::raw::{c_int, c_void},
+    panic::{self, AssertUnwindSafe},
+    time::Duration,
+};
+
+/// Buffer size of a received Ethernet frame
+const EOE_MAX_FRAME_SIZE: usize = 1536;
+
+pub(crate) type ConfigHook = Box<dyn FnMut(&mut HookCtx<'_>, u16) -> i32 + Send>;
+pub(crate) type FoeHook = Box<dyn FnMut(u16, i32, i32) + Send>;
+pub(crate) type EoeHook = Box<dyn FnMut(u16, &[u8]) + Send>;
+
+/// Data that is passed to the SOEM callbacks via `ecx_context.userdata`
+#[derive(Default)]
+pub(crate) struct UserData {
+    pub config_hook: Option<ConfigHook>,
+    pub foe_hook: Option<FoeHook>,
+    pub eoe_hook: Option<EoeHook>,
+    /// Frames that are reassembled for the EoE hook (by slave)
+    pub eoe_frames: HashMap<u16, EoeFrame>,
+}
+
+/// Reassembly of a fragmented EoE frame
+pub(crate) struct EoeFrame {
+    fragment_no: u8,
+    frame_size: u16,
+    frame_offset: u16,
+    frame_no: u16,
+    buf: Vec<u8>,
+}
+
+impl Default for EoeFrame {
+    fn default() -> Self {
+        Self {
+            fragment_no: 0,
+            frame_size: 0,
+            frame_offset: 0,
+            frame_no: 0,
+            buf: vec![0; EOE_MAX_FRAME_SIZE],
+        }
+    }
+}
+
+thread_local! {
+    // SOEM doesn't pass the context to the FoE hook,
+    // so it's only available during an FoE transfer of this thread.
+    static FOE_HOOK: RefCell<Option<FoeHook>> = RefCell::new(None);
+}
+
+/// Access to the SOEM context within a hook
+#[allow(missing_debug_implementations)]
+pub struct HookCtx<'a> {
+    ecx_ctx: &'a mut sys::ecx_context,
+}
+
+impl HookCtx<'_> {
+    pub fn sdo_read<'t>(
+        &mut self,
+        slave: u16,
+        idx: u16,
+        sub_idx: u8,
+        access_complete: bool,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> (i32, &'t mut [u8]) {
+        crate::sdo_read(
+            self.ecx_ctx,
+            slave,
+            idx,
+            sub_idx,
+            access_complete,
+            target,
+            timeout,
+        )
+    }
+    pub fn sdo_write(
+        &mut self,
+        slave: u16,
+        idx: u16,
+        sub_idx: u8,
+        access_complete: bool,
+        data: &[u8],
+        timeout: Duration,
+    ) -> i32 {
+        crate::sdo_write(
+            self.ecx_ctx,
+            slave,
+            idx,
+            sub_idx,
+            access_complete,
+            data,
+            timeout,
+        )
+    }
+    /// Pop an error of the context, e.g. the abort code of a failed SDO transfer.
+    pub fn pop_error(&mut self) -> Option<crate::Error> {
+        let mut ec: sys::ec_errort = unsafe { std::mem::zeroed() };
+        if unsafe { sys::ecx_poperror(self.ecx_ctx, &mut ec) } != 0 {
+            Some(crate::Error::from(ec))
+        } else {
+            None
+        }
+    }
+}
+
+/// Run a user hook.
+///
+/// A panic must not unwind into SOEM,
+/// so it's caught and reported as failure (`0`).
+fn call_hook<F: FnOnce() -> c_int>(hook: F) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(hook)).unwrap_or(0)
+}
+
+pub(crate) unsafe extern "C" fn po2so_config(context: *mut sys::ecx_contextt, slave: u16) -> c_int {
+    let user_data = (*context).userdata as *mut UserData;
+    if user_data.is_null() {
+        return 0;
+    }
+    match &mut (*user_data).config_hook {
+        Some(hook) => {
+            let mut ctx = HookCtx {
+                ecx_ctx: &mut *context,
+            };
+            call_hook(|| hook(&mut ctx, slave))
+        }
+        None => 0,
+    }
+}
+
+/// Run an FoE transfer with the hook installed for the current thread.
+pub(crate) fn with_foe_hook<T, F>(hook: &mut Option<FoeHook>, transfer: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    FOE_HOOK.with(|h| *h.borrow_mut() = hook.take());
+    let res = transfer();
+    *hook = FOE_HOOK.with(|h| h.borrow_mut().take());
+    res
+}
+
+pub(crate) unsafe extern "C" fn foe_progress(slave: u16, packet: c_int, size: c_int) -> c_int {
+    FOE_HOOK.with(|h| {
+        if let Some(hook) = h.borrow_mut().as_mut() {
+            call_hook(|| {
+                hook(slave, packet, size);
+                0
+            });
+        }
+    });
+    0
+}
+
+pub(crate) unsafe extern "C" fn eoe_fragment(
+    context: *mut sys::ecx_contextt,
+    slave: u16,
+    eoembx: *mut c_void,
+) -> c_int {
+    let user_data = (*context).userdata as *mut UserData;
+    if user_data.is_null() {
+        return 0;
+    }
+    let user_data = &mut *user_data;
+    let hook = match &mut user_data.eoe_hook {
+        Some(hook) => hook,
+        None => return 0,
+    };
+    let frame = user_data.eoe_frames.entry(slave).or_default();
+    let mut size = frame.buf.len() as c_int;
+    let complete = sys::ecx_EOEreadfragment(
+        eoembx as *mut sys::ec_mbxbuft,
+        &mut frame.fragment_no,
+        &mut frame.frame_size,
+        &mut frame.frame_offset,
+        &mut frame.frame_no,
+        &mut size,
+        frame.buf.as_mut_ptr() as *mut c_void,
+    );
+    if complete > 0 {
+        let len = (size.max(0) as usize).min(frame.buf.len());
+        call_hook(|| {
+            hook(slave, &frame.buf[..len]);
+            0
+        });
+        *frame = EoeFrame::default();
+    }
+    // The fragment is consumed.
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn catch_a_panicking_hook() {
+        let mut ctx = Ctx::default();
+        ctx.set_config_hook(|_, _| panic!("PDO config failed"));
+        let res = unsafe { po2so_config(&mut ctx.ecx_ctx, 1) };
+        assert_eq!(res, 0);
+    }
+}
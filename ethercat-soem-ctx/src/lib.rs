@@ -17,22 +17,65 @@ use ethercat_soem_sys as sys;
 use std::{
     ffi::{CStr, CString},
     mem::{self, zeroed},
-    os::raw::c_void,
+    ops::Range,
+    os::raw::{c_char, c_int, c_void},
+    ptr,
     time::Duration,
 };
 
+mod eoe;
 mod error;
 mod group;
+mod hook;
 mod od_list;
 mod oe_list;
+mod process_data;
 mod slave;
 mod sm;
 
-pub use crate::{error::*, group::*, od_list::*, oe_list::*, slave::*};
+pub use crate::{
+    eoe::EoeIpParam, error::*, group::*, hook::HookCtx, od_list::*, oe_list::*,
+    process_data::ProcessData, slave::*,
+};
+
+use crate::hook::UserData;
 
+/// Default number of groups
 const EC_MAX_GROUP: usize = 2;
+
+/// Default size of the slave list (including the master at index 0)
 const EC_MAX_SLAVE: usize = 200;
 
+/// ESC register: type
+const ECT_REG_TYPE: u16 = 0x0000;
+
+/// ESC register: SM0 (followed by SM1)
+const ECT_REG_SM0: u16 = 0x0800;
+
+/// SII word address: bootstrap receive mailbox (offset and size)
+const ECT_SII_BOOTRXMBX: u16 = 0x0014;
+
+/// SII word address: bootstrap send mailbox (offset and size)
+const ECT_SII_BOOTTXMBX: u16 = 0x0016;
+
+/// SII word address: standard receive mailbox (offset and size)
+const ECT_SII_MBXRX: u16 = 0x0018;
+
+/// SII word address: standard send mailbox (offset and size)
+const ECT_SII_MBXTX: u16 = 0x001A;
+
+/// SM flags of the mailbox the master writes to
+const EC_SMFLAGS_MBX_WRITE: u32 = 0x0001_0026;
+
+/// SM flags of the mailbox the master reads from
+const EC_SMFLAGS_MBX_READ: u32 = 0x0001_0022;
+
+/// Timeout for safe data transfer
+const EC_TIMEOUTSAFE: i32 = 20_000;
+
+/// Return value of `config_init` if there are more slaves than the slave list can hold
+pub const EC_SLAVECOUNTEXCEEDED: i32 = -3;
+
 /// Size of EEPROM bitmap cache
 const EC_MAX_EEP_BITMAP: usize = 128;
 
@@ -44,9 +87,11 @@ const EC_MAX_EEP_BUF: usize = EC_MAX_EEP_BITMAP << 5;
 pub struct Ctx {
     #[allow(dead_code)]
     port: Box<sys::ecx_portt>,
-    slave_list: Box<[Slave; EC_MAX_SLAVE]>,
+    /// Secondary port (only used in redundant mode)
+    red_port: Option<Box<sys::ecx_redportt>>,
+    slave_list: Box<[Slave]>,
     slave_count: Box<i32>,
-    group_list: Box<[Group; EC_MAX_GROUP]>,
+    group_list: Box<[Group]>,
     #[allow(dead_code)]
     esi_buf: Box<[u8; EC_MAX_EEP_BUF]>,
     #[allow(dead_code)]
@@ -72,18 +117,77 @@ pub struct Ctx {
     /// The original context
     ecx_ctx: sys::ecx_context,
 
-    /// I/O map
-    pub io_map: [u8; 4096],
+    /// I/O maps of the groups
+    io_maps: Vec<Vec<u8>>,
+
+    /// Data for the hooks (referenced by `ecx_ctx.userdata`)
+    user_data: Box<UserData>,
 }
 
+// All raw pointers inside `ecx_ctx` and the slave/group lists point to
+// memory that is owned by this struct, so it's safe to move it to
+// another thread.
+unsafe impl Send for Ctx {}
+
 impl Default for Ctx {
     fn default() -> Self {
+        CtxBuilder::default().build()
+    }
+}
+
+/// Builder for a [`Ctx`] with custom limits
+///
+/// The slave and group lists are allocated on the heap
+/// with the configured sizes.
+///
+/// Note: SOEM refuses networks with `EC_MAXSLAVE` (= 200) or more slaves
+/// while scanning the bus, regardless of the size of the slave list.
+/// To run larger lines SOEM has to be built with a higher `EC_MAXSLAVE`
+/// by setting the environment variable of the same name
+/// while building `ethercat-soem-sys`.
+#[derive(Debug, Clone)]
+pub struct CtxBuilder {
+    max_slaves: usize,
+    max_groups: usize,
+}
+
+impl Default for CtxBuilder {
+    fn default() -> Self {
+        Self {
+            max_slaves: EC_MAX_SLAVE - 1,
+            max_groups: EC_MAX_GROUP,
+        }
+    }
+}
+
+impl CtxBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Maximum number of slaves (without the master)
+    #[must_use]
+    pub fn max_slaves(mut self, max_slaves: usize) -> Self {
+        self.max_slaves = max_slaves;
+        self
+    }
+    /// Maximum number of groups (at least one)
+    #[must_use]
+    pub fn max_groups(mut self, max_groups: usize) -> Self {
+        self.max_groups = max_groups;
+        self
+    }
+    #[must_use]
+    pub fn build(self) -> Ctx {
+        // Index 0 of the slave list is reserved for the master.
+        let slave_list_len = self.max_slaves + 1;
+        let group_list_len = self.max_groups.max(1);
         let mut port = Box::new(sys::ecx_portt {
             _bindgen_opaque_blob: [0; 6502],
         });
-        let mut slave_list: Box<[Slave; EC_MAX_SLAVE]> = Box::new(unsafe { zeroed() });
+        let mut slave_list: Box<[Slave]> = (0..slave_list_len).map(|_| Slave::default()).collect();
         let mut slave_count = Box::new(0);
-        let mut group_list: Box<[Group; EC_MAX_GROUP]> = Box::new(unsafe { zeroed() });
+        let mut group_list: Box<[Group]> = (0..group_list_len).map(|_| Group::default()).collect();
         let mut esi_buf: Box<[u8; EC_MAX_EEP_BUF]> = Box::new([0; EC_MAX_EEP_BUF]);
         let mut esi_map: Box<[u32; EC_MAX_EEP_BITMAP]> = Box::new([0; EC_MAX_EEP_BITMAP]);
         let mut e_list: Box<[u32; 456]> = Box::new([0; 456]);
@@ -95,16 +199,17 @@ impl Default for Ctx {
         let mut pdo_desc: Box<[u8; 1026]> = Box::new([0; 1026]);
         let mut eep_sm: Box<[u16; 6]> = Box::new([0; 6]);
         let mut eep_fmmu: Box<[u16; 4]> = Box::new([0; 4]);
-        let io_map = [0; 4096];
+        let io_maps = vec![vec![]; group_list_len];
+        let mut user_data = Box::new(UserData::default());
 
         // The original context
         let ecx_ctx = sys::ecx_context {
             port: &mut *port,
             slavelist: slave_list.as_mut_ptr() as *mut sys::ec_slave,
             slavecount: &mut *slave_count,
-            maxslave: EC_MAX_SLAVE as i32,
+            maxslave: slave_list_len as i32,
             grouplist: group_list.as_mut_ptr() as *mut sys::ec_group,
-            maxgroup: EC_MAX_GROUP as i32,
+            maxgroup: group_list_len as i32,
             esibuf: esi_buf.as_mut_ptr(),
             esimap: esi_map.as_mut_ptr(),
             esislave: 0,
@@ -120,11 +225,12 @@ impl Default for Ctx {
             FOEhook: None,
             EOEhook: None,
             manualstatechange: 0,
-            userdata: std::ptr::null_mut(),
+            userdata: &mut *user_data as *mut UserData as *mut c_void,
         };
 
-        Self {
+        Ctx {
             port,
+            red_port: None,
             slave_list,
             slave_count,
             group_list,
@@ -140,7 +246,8 @@ impl Default for Ctx {
             eep_sm,
             eep_fmmu,
             ecx_ctx,
-            io_map,
+            io_maps,
+            user_data,
         }
     }
 }
@@ -152,33 +259,280 @@ impl Ctx {
     pub fn init(&mut self, iface: CString) -> i32 {
         unsafe { sys::ecx_init(&mut self.ecx_ctx, iface.as_ptr()) }
     }
+    /// Initialise lib in redundant NIC mode.
+    ///
+    /// Frames are sent on both interfaces, so a ring
+    /// keeps working if the cable is cut at one point.
+    ///
+    /// Return > 0 if OK.
+    pub fn init_redundant(&mut self, iface: CString, iface2: CString) -> i32 {
+        let red_port = self
+            .red_port
+            .get_or_insert_with(|| Box::new(unsafe { zeroed() }));
+        unsafe {
+            sys::ecx_init_redundant(
+                &mut self.ecx_ctx,
+                &mut **red_port,
+                iface.as_ptr(),
+                iface2.as_ptr() as *mut _,
+            )
+        }
+    }
+    /// Returns `true` if the context was initialised in redundant mode.
+    pub const fn is_redundant(&self) -> bool {
+        self.red_port.is_some()
+    }
+    /// Enumerate and init all slaves.
+    ///
+    /// It returns the number of slaves found,
+    /// or [`EC_SLAVECOUNTEXCEEDED`] if they don't fit into the slave list.
     pub fn config_init(&mut self, use_table: bool) -> i32 {
+        // SOEM only checks the number of slaves against its
+        // compile time limit, not against the size of our slave list.
+        let mut data = [0_u8; 2];
+        let cnt = unsafe {
+            sys::ecx_BRD(
+                self.ecx_ctx.port,
+                0x0000,
+                ECT_REG_TYPE,
+                data.len() as u16,
+                data.as_mut_ptr() as *mut c_void,
+                EC_TIMEOUTSAFE,
+            )
+        };
+        if cnt >= self.ecx_ctx.maxslave {
+            return EC_SLAVECOUNTEXCEEDED;
+        }
         unsafe { sys::ecx_config_init(&mut self.ecx_ctx, if use_table { 1 } else { 0 }) }
     }
+    /// Map all PDOs of a group into the I/O map of the group.
+    ///
+    /// Group 0 contains all slaves.
+    ///
+    /// SOEM only calculates the addresses of the process data
+    /// while mapping and does not write to the I/O map,
+    /// so the map is resized to the required size afterwards.
+    ///
+    /// It returns the size of the I/O map.
     pub fn config_map_group(&mut self, group: u8) -> i32 {
+        let base = self.io_maps[group as usize].as_mut_ptr();
+        let size =
+            unsafe { sys::ecx_config_map_group(&mut self.ecx_ctx, base as *mut c_void, group) };
+        if size > 0 {
+            self.resize_io_map(group, base, size as usize);
+        }
+        size
+    }
+    /// Map all PDOs of a group into the I/O map of the group
+    /// with overlapping inputs and outputs.
+    ///
+    /// The inputs and outputs of a slave share the same logical
+    /// address range, so a single LRW datagram serves both.
+    /// In the I/O map the inputs are still placed behind the outputs.
+    ///
+    /// It returns the size of the I/O map.
+    pub fn config_overlap_map_group(&mut self, group: u8) -> i32 {
+        let base = self.io_maps[group as usize].as_mut_ptr();
+        let size = unsafe {
+            sys::ecx_config_overlap_map_group(&mut self.ecx_ctx, base as *mut c_void, group)
+        };
+        if size > 0 {
+            self.resize_io_map(group, base, size as usize);
+        }
+        size
+    }
+    /// Reserve memory for the I/O map of a group,
+    /// so that mapping up to `capacity` bytes does not reallocate it.
+    pub fn reserve_io_map(&mut self, group: u8, capacity: usize) {
+        let io_map = &mut self.io_maps[group as usize];
+        io_map.reserve_exact(capacity.saturating_sub(io_map.len()));
+    }
+    pub fn io_map(&self, group: u8) -> &[u8] {
+        &self.io_maps[group as usize]
+    }
+    pub fn io_map_mut(&mut self, group: u8) -> &mut [u8] {
+        &mut self.io_maps[group as usize]
+    }
+    #[doc(hidden)]
+    /// Don't use this!
+    ///
+    /// Place the inputs and outputs of the given slaves (by slave number)
+    /// in the I/O map of a group as if SOEM had mapped the group.
+    pub fn fake_map_group(
+        &mut self,
+        group: u8,
+        io_map: Vec<u8>,
+        slaves: &[(u16, Range<usize>, Range<usize>)],
+    ) {
+        self.io_maps[group as usize] = io_map;
+        let base = self.io_maps[group as usize].as_mut_ptr();
+        let data = |range: &Range<usize>| {
+            if range.is_empty() {
+                ptr::null_mut()
+            } else {
+                base.wrapping_add(range.start)
+            }
+        };
+        for (i, inputs, outputs) in slaves {
+            *self.slave_count = (*self.slave_count).max(i32::from(*i));
+            let slave = &mut self.slave_list[*i as usize].0;
+            slave.group = group;
+            slave.Ibytes = inputs.len() as u32;
+            slave.inputs = data(inputs);
+            slave.Obytes = outputs.len() as u32;
+            slave.outputs = data(outputs);
+        }
+    }
+    /// Resize the I/O map and move the
+    /// process data pointers if it was reallocated.
+    fn resize_io_map(&mut self, group: u8, base: *mut u8, size: usize) {
+        let io_map = &mut self.io_maps[group as usize];
+        io_map.resize(size, 0);
+        let new_base = io_map.as_mut_ptr();
+        if new_base == base {
+            return;
+        }
+        let relocate = |ptr: &mut *mut u8| {
+            if !ptr.is_null() {
+                let offset = *ptr as usize - base as usize;
+                *ptr = new_base.wrapping_add(offset);
+            }
+        };
+        let slave_count = self.slave_count();
+        // group 0 contains all slaves
+        for slave in self.slave_list[..=slave_count]
+            .iter_mut()
+            .filter(|s| group == 0 || s.group() == group)
+        {
+            relocate(&mut slave.0.outputs);
+            relocate(&mut slave.0.inputs);
+        }
+        let group = &mut self.group_list[group as usize];
+        relocate(&mut group.0.outputs);
+        relocate(&mut group.0.inputs);
+    }
+    pub fn config_dc(&mut self) -> u8 {
+        unsafe { sys::ecx_configdc(&mut self.ecx_ctx) }
+    }
+    /// Set DC of a slave to fire SYNC0 at `cycle_time` interval
+    /// with time `shift` (in ns).
+    pub fn dc_sync0(&mut self, slave: u16, active: bool, cycle_time: u32, shift: i32) {
         unsafe {
-            sys::ecx_config_map_group(
+            sys::ecx_dcsync0(
                 &mut self.ecx_ctx,
-                self.io_map.as_mut_ptr() as *mut std::ffi::c_void,
-                group,
+                slave,
+                if active { 1 } else { 0 },
+                cycle_time,
+                shift,
             )
         }
     }
-    pub fn config_dc(&mut self) -> u8 {
-        unsafe { sys::ecx_configdc(&mut self.ecx_ctx) }
+    /// Set DC of a slave to fire SYNC0 and SYNC1 at `cycle_time0` interval
+    /// with time `shift` (in ns).
+    ///
+    /// SYNC1 fires `cycle_time1` after SYNC0.
+    pub fn dc_sync01(
+        &mut self,
+        slave: u16,
+        active: bool,
+        cycle_time0: u32,
+        cycle_time1: u32,
+        shift: i32,
+    ) {
+        unsafe {
+            sys::ecx_dcsync01(
+                &mut self.ecx_ctx,
+                slave,
+                if active { 1 } else { 0 },
+                cycle_time0,
+                cycle_time1,
+                shift,
+            )
+        }
+    }
+    /// Recover a slave that lost its configured address
+    /// (e.g. after it was power cycled or re-plugged).
+    ///
+    /// Returns > 0 if successful.
+    pub fn recover_slave(&mut self, slave: u16, timeout: Duration) -> i32 {
+        unsafe { sys::ecx_recover_slave(&mut self.ecx_ctx, slave, timeout.as_micros() as i32) }
+    }
+    /// Reconfigure a slave: bring it to PRE-OP, run the PO->SO hook,
+    /// restore SM & FMMU settings and request SAFE-OP.
+    ///
+    /// It returns the slave state.
+    pub fn reconfig_slave(&mut self, slave: u16, timeout: Duration) -> i32 {
+        unsafe { sys::ecx_reconfig_slave(&mut self.ecx_ctx, slave, timeout.as_micros() as i32) }
+    }
+    /// Read 4 bytes from the EEPROM of a slave.
+    ///
+    /// The EEPROM control is handed over to the master.
+    /// `addr` is a word address.
+    pub fn read_eeprom(&mut self, slave: u16, addr: u16, timeout: Duration) -> u32 {
+        unsafe { sys::ecx_readeeprom(&mut self.ecx_ctx, slave, addr, timeout.as_micros() as i32) }
+    }
+    /// Read 4 or 8 bytes (see [`Slave::eep_8byte`]) from the EEPROM
+    /// of a slave by its configured address.
+    ///
+    /// The EEPROM has to be controlled by the master
+    /// (see [`Ctx::eeprom_to_master`]).
+    pub fn read_eeprom_fp(&mut self, config_addr: u16, addr: u16, timeout: Duration) -> u64 {
+        unsafe {
+            sys::ecx_readeepromFP(
+                &mut self.ecx_ctx,
+                config_addr,
+                addr,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Write a word to the EEPROM of a slave.
+    ///
+    /// The EEPROM control is handed over to the master.
+    /// Returns > 0 if successful.
+    pub fn write_eeprom(&mut self, slave: u16, addr: u16, data: u16, timeout: Duration) -> i32 {
+        unsafe {
+            sys::ecx_writeeeprom(
+                &mut self.ecx_ctx,
+                slave,
+                addr,
+                data,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Invalidate the SII data that SOEM caches
+    /// (e.g. after writing the EEPROM).
+    pub fn clear_sii_cache(&mut self) {
+        self.ecx_ctx.esislave = 0;
+    }
+    /// Hand the EEPROM control of a slave over to the master.
+    ///
+    /// Returns > 0 if successful.
+    pub fn eeprom_to_master(&mut self, slave: u16) -> i32 {
+        unsafe { sys::ecx_eeprom2master(&mut self.ecx_ctx, slave) }
+    }
+    /// Hand the EEPROM control of a slave over to the PDI.
+    ///
+    /// Returns > 0 if successful.
+    pub fn eeprom_to_pdi(&mut self, slave: u16) -> i32 {
+        unsafe { sys::ecx_eeprom2pdi(&mut self.ecx_ctx, slave) }
     }
     pub const fn slave_count(&self) -> usize {
         *self.slave_count as usize
     }
-    pub fn slaves(&self) -> &[Slave; EC_MAX_SLAVE] {
+    pub fn slaves(&self) -> &[Slave] {
         &self.slave_list
     }
-    pub fn slaves_mut(&mut self) -> &mut [Slave; EC_MAX_SLAVE] {
+    pub fn slaves_mut(&mut self) -> &mut [Slave] {
         &mut self.slave_list
     }
-    pub const fn groups(&self) -> &[Group; EC_MAX_GROUP] {
+    pub const fn groups(&self) -> &[Group] {
         &self.group_list
     }
+    pub fn groups_mut(&mut self) -> &mut [Group] {
+        &mut self.group_list
+    }
     /// Write slave state, if slave = 0 then write to all slaves.
     ///
     /// The function does not check if the actual state is changed.
@@ -209,6 +563,17 @@ impl Ctx {
     pub fn receive_processdata(&mut self, timeout: Duration) -> i32 {
         unsafe { sys::ecx_receive_processdata(&mut self.ecx_ctx, timeout.as_micros() as i32) }
     }
+    pub fn send_processdata_group(&mut self, group: u8) -> i32 {
+        unsafe { sys::ecx_send_processdata_group(&mut self.ecx_ctx, group) }
+    }
+    pub fn send_overlap_processdata_group(&mut self, group: u8) -> i32 {
+        unsafe { sys::ecx_send_overlap_processdata_group(&mut self.ecx_ctx, group) }
+    }
+    pub fn receive_processdata_group(&mut self, group: u8, timeout: Duration) -> i32 {
+        unsafe {
+            sys::ecx_receive_processdata_group(&mut self.ecx_ctx, group, timeout.as_micros() as i32)
+        }
+    }
     pub fn read_od_list(&mut self, slave: u16, od_list: &mut OdList) -> i32 {
         unsafe { sys::ecx_readODlist(&mut self.ecx_ctx, slave, &mut od_list.0) }
     }
@@ -227,47 +592,322 @@ impl Ctx {
         target: &'t mut [u8],
         timeout: Duration,
     ) -> (i32, &'t mut [u8]) {
-        let mut size = mem::size_of_val(target) as i32;
-        let timeout = timeout.as_micros() as i32; //TODO: check overflow
+        sdo_read(
+            &mut self.ecx_ctx,
+            slave,
+            idx,
+            sub_idx,
+            access_complete,
+            target,
+            timeout,
+        )
+    }
+    pub fn sdo_write(
+        &mut self,
+        slave: u16,
+        idx: u16,
+        sub_idx: u8,
+        access_complete: bool,
+        data: &[u8],
+        timeout: Duration,
+    ) -> i32 {
+        sdo_write(
+            &mut self.ecx_ctx,
+            slave,
+            idx,
+            sub_idx,
+            access_complete,
+            data,
+            timeout,
+        )
+    }
+    /// Register a hook that is called with each Ethernet frame
+    /// a slave sends via EoE.
+    ///
+    /// The fragments are received with any mailbox transfer
+    /// of the slave (e.g. [`Ctx::eoe_recv`]).
+    pub fn set_eoe_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(u16, &[u8]) + Send + 'static,
+    {
+        self.user_data.eoe_hook = Some(Box::new(hook));
+        self.ecx_ctx.EOEhook = Some(hook::eoe_fragment);
+    }
+    /// Set the IP parameters of an EoE port of a slave.
+    ///
+    /// Returns the working counter.
+    pub fn eoe_set_ip(
+        &mut self,
+        slave: u16,
+        port: u8,
+        param: &EoeIpParam,
+        timeout: Duration,
+    ) -> i32 {
+        let mut param = param.to_sys();
+        unsafe {
+            sys::ecx_EOEsetIp(
+                &mut self.ecx_ctx,
+                slave,
+                port,
+                &mut param,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Send an Ethernet frame to a slave via EoE.
+    ///
+    /// The frame is split into fragments that fit into the mailbox.
+    /// Returns the working counter.
+    pub fn eoe_send(&mut self, slave: u16, port: u8, frame: &[u8], timeout: Duration) -> i32 {
+        unsafe {
+            sys::ecx_EOEsend(
+                &mut self.ecx_ctx,
+                slave,
+                port,
+                frame.len() as c_int,
+                frame.as_ptr() as *mut c_void,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Receive an Ethernet frame from a slave via EoE.
+    ///
+    /// If an EoE hook is registered, the frame is passed to the hook instead.
+    /// Returns the working counter and the received part of `target`.
+    pub fn eoe_recv<'t>(
+        &mut self,
+        slave: u16,
+        port: u8,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> (i32, &'t mut [u8]) {
+        let mut size = target.len() as c_int;
         let wkc = unsafe {
-            sys::ecx_SDOread(
+            sys::ecx_EOErecv(
                 &mut self.ecx_ctx,
                 slave,
-                idx,
-                sub_idx,
-                if access_complete { 1 } else { 0 },
+                port,
                 &mut size,
                 target.as_mut_ptr() as *mut c_void,
-                timeout,
+                timeout.as_micros() as i32,
             )
         };
-        if wkc <= 0 {
-            (wkc, target)
-        } else {
-            (wkc, &mut target[..size as usize])
-        }
+        let len = (size.max(0) as usize).min(target.len());
+        (wkc, &mut target[..len])
     }
-    pub fn sdo_write(
+    /// Read elements of an IDN of a drive via SoE.
+    ///
+    /// Returns the working counter and the read part of `target`.
+    pub fn soe_read<'t>(
         &mut self,
         slave: u16,
-        idx: u16,
-        sub_idx: u8,
-        access_complete: bool,
+        drive_no: u8,
+        elements: u8,
+        idn: u16,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> (i32, &'t mut [u8]) {
+        let mut size = target.len() as c_int;
+        let wkc = unsafe {
+            sys::ecx_SoEread(
+                &mut self.ecx_ctx,
+                slave,
+                drive_no,
+                elements,
+                idn,
+                &mut size,
+                target.as_mut_ptr() as *mut c_void,
+                timeout.as_micros() as i32,
+            )
+        };
+        let len = (size.max(0) as usize).min(target.len());
+        (wkc, &mut target[..len])
+    }
+    /// Write elements of an IDN of a drive via SoE.
+    ///
+    /// Returns the working counter.
+    pub fn soe_write(
+        &mut self,
+        slave: u16,
+        drive_no: u8,
+        elements: u8,
+        idn: u16,
         data: &[u8],
         timeout: Duration,
     ) -> i32 {
-        let size = mem::size_of_val(data) as i32;
-        let timeout = timeout.as_micros() as i32; //TODO: check overflow
         unsafe {
-            sys::ecx_SDOwrite(
+            sys::ecx_SoEwrite(
                 &mut self.ecx_ctx,
                 slave,
-                idx,
-                sub_idx,
-                if access_complete { 1 } else { 0 },
-                size,
+                drive_no,
+                elements,
+                idn,
+                data.len() as c_int,
                 data.as_ptr() as *mut c_void,
-                timeout,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Read the size of the SoE process data (MDT and AT) of a slave in bits.
+    ///
+    /// Returns the working counter, the output and the input size.
+    pub fn read_idn_map(&mut self, slave: u16) -> (i32, u32, u32) {
+        let mut output_bits = 0;
+        let mut input_bits = 0;
+        let wkc = unsafe {
+            sys::ecx_readIDNmap(&mut self.ecx_ctx, slave, &mut output_bits, &mut input_bits)
+        };
+        (wkc, output_bits.max(0) as u32, input_bits.max(0) as u32)
+    }
+    /// Register a hook that is called for each slave
+    /// in the transition PRE-OP -> SAFE-OP
+    /// (SOEM `PO2SOconfigx`), e.g. to configure the PDO mapping.
+    ///
+    /// The hook is called while mapping the process data and
+    /// when a slave is reconfigured. It has to be registered
+    /// after `config_init` because that resets the slave list.
+    pub fn set_config_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&mut HookCtx<'_>, u16) -> i32 + Send + 'static,
+    {
+        self.user_data.config_hook = Some(Box::new(hook));
+        let slave_count = self.slave_count();
+        for slave in &mut self.slave_list[1..=slave_count] {
+            slave.0.PO2SOconfigx = Some(hook::po2so_config);
+        }
+    }
+    /// Register a hook that is called for each FoE packet
+    /// with the slave, the packet number and the data size.
+    ///
+    /// The hook is called by the thread that transfers the file.
+    pub fn set_foe_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(u16, i32, i32) + Send + 'static,
+    {
+        self.user_data.foe_hook = Some(Box::new(hook));
+        self.ecx_ctx.FOEhook = Some(hook::foe_progress);
+    }
+    /// Read a file from a slave via FoE.
+    ///
+    /// Returns the working counter and the read part of `target`.
+    pub fn foe_read<'t>(
+        &mut self,
+        slave: u16,
+        filename: &CStr,
+        password: u32,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> (i32, &'t mut [u8]) {
+        let mut size = target.len() as c_int;
+        let ecx_ctx = &mut self.ecx_ctx;
+        let wkc = hook::with_foe_hook(&mut self.user_data.foe_hook, || unsafe {
+            sys::ecx_FOEread(
+                ecx_ctx,
+                slave,
+                filename.as_ptr() as *mut c_char,
+                password,
+                &mut size,
+                target.as_mut_ptr() as *mut c_void,
+                timeout.as_micros() as i32,
+            )
+        });
+        let len = (size.max(0) as usize).min(target.len());
+        (wkc, &mut target[..len])
+    }
+    /// Write a file to a slave via FoE.
+    ///
+    /// Returns the working counter.
+    pub fn foe_write(
+        &mut self,
+        slave: u16,
+        filename: &CStr,
+        password: u32,
+        data: &[u8],
+        timeout: Duration,
+    ) -> i32 {
+        let ecx_ctx = &mut self.ecx_ctx;
+        hook::with_foe_hook(&mut self.user_data.foe_hook, || unsafe {
+            sys::ecx_FOEwrite(
+                ecx_ctx,
+                slave,
+                filename.as_ptr() as *mut c_char,
+                password,
+                data.len() as c_int,
+                data.as_ptr() as *mut c_void,
+                timeout.as_micros() as i32,
+            )
+        })
+    }
+    /// Configure the bootstrap mailbox (SM0 and SM1) of a slave
+    /// according to its EEPROM, as required by the BOOT state.
+    ///
+    /// It returns the working counter or `EC_NOFRAME` (= `-1`).
+    pub fn config_boot_mailbox(&mut self, slave: u16, timeout: Duration) -> i32 {
+        self.config_mailbox(slave, ECT_SII_BOOTRXMBX, ECT_SII_BOOTTXMBX, timeout)
+    }
+    /// Configure the standard mailbox (SM0 and SM1) of a slave
+    /// according to its EEPROM, e.g. after leaving the BOOT state.
+    ///
+    /// It returns the working counter or `EC_NOFRAME` (= `-1`).
+    pub fn config_standard_mailbox(&mut self, slave: u16, timeout: Duration) -> i32 {
+        self.config_mailbox(slave, ECT_SII_MBXRX, ECT_SII_MBXTX, timeout)
+    }
+    fn config_mailbox(&mut self, slave: u16, rx_addr: u16, tx_addr: u16, timeout: Duration) -> i32 {
+        let rx = self.read_eeprom(slave, rx_addr, timeout);
+        let tx = self.read_eeprom(slave, tx_addr, timeout);
+        let s = &mut self.slave_list[slave as usize].0;
+        s.SM[0].StartAddr = rx as u16;
+        s.SM[0].SMlength = (rx >> 16) as u16;
+        s.SM[0].SMflags = EC_SMFLAGS_MBX_WRITE;
+        s.SM[1].StartAddr = tx as u16;
+        s.SM[1].SMlength = (tx >> 16) as u16;
+        s.SM[1].SMflags = EC_SMFLAGS_MBX_READ;
+        s.mbx_wo = s.SM[0].StartAddr;
+        s.mbx_l = s.SM[0].SMlength;
+        s.mbx_ro = s.SM[1].StartAddr;
+        s.mbx_rl = s.SM[1].SMlength;
+        let mut sms = [s.SM[0], s.SM[1]];
+        unsafe {
+            sys::ecx_FPWR(
+                self.ecx_ctx.port,
+                s.configadr,
+                ECT_REG_SM0,
+                mem::size_of_val(&sms) as u16,
+                sms.as_mut_ptr() as *mut c_void,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Receive a message that a slave sent without request
+    /// (e.g. an emergency) if its mailbox is full.
+    ///
+    /// SOEM stores emergencies in the error list and passes
+    /// EoE fragments to the EoE hook; other messages are dropped.
+    /// It returns the working counter (`0` if no message or an emergency
+    /// was received).
+    pub fn mbx_receive(&mut self, slave: u16, timeout: Duration) -> i32 {
+        let mut mbx: sys::ec_mbxbuft = unsafe { zeroed() };
+        unsafe {
+            sys::ecx_mbxreceive(
+                &mut self.ecx_ctx,
+                slave,
+                &mut mbx,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    /// Configured address physical read of a slave register.
+    ///
+    /// It returns the working counter or `EC_NOFRAME` (= `-1`).
+    pub fn fprd(&mut self, config_addr: u16, reg: u16, data: &mut [u8], timeout: Duration) -> i32 {
+        unsafe {
+            sys::ecx_FPRD(
+                self.ecx_ctx.port,
+                config_addr,
+                reg,
+                data.len() as u16,
+                data.as_mut_ptr() as *mut c_void,
+                timeout.as_micros() as i32,
             )
         }
     }
@@ -290,6 +930,61 @@ impl Ctx {
     }
 }
 
+fn sdo_read<'t>(
+    ecx_ctx: &mut sys::ecx_context,
+    slave: u16,
+    idx: u16,
+    sub_idx: u8,
+    access_complete: bool,
+    target: &'t mut [u8],
+    timeout: Duration,
+) -> (i32, &'t mut [u8]) {
+    let mut size = mem::size_of_val(target) as i32;
+    let timeout = timeout.as_micros() as i32; //TODO: check overflow
+    let wkc = unsafe {
+        sys::ecx_SDOread(
+            ecx_ctx,
+            slave,
+            idx,
+            sub_idx,
+            if access_complete { 1 } else { 0 },
+            &mut size,
+            target.as_mut_ptr() as *mut c_void,
+            timeout,
+        )
+    };
+    if wkc <= 0 {
+        (wkc, target)
+    } else {
+        (wkc, &mut target[..size as usize])
+    }
+}
+
+fn sdo_write(
+    ecx_ctx: &mut sys::ecx_context,
+    slave: u16,
+    idx: u16,
+    sub_idx: u8,
+    access_complete: bool,
+    data: &[u8],
+    timeout: Duration,
+) -> i32 {
+    let size = mem::size_of_val(data) as i32;
+    let timeout = timeout.as_micros() as i32; //TODO: check overflow
+    unsafe {
+        sys::ecx_SDOwrite(
+            ecx_ctx,
+            slave,
+            idx,
+            sub_idx,
+            if access_complete { 1 } else { 0 },
+            size,
+            data.as_ptr() as *mut c_void,
+            timeout,
+        )
+    }
+}
+
 fn c_array_to_string(data: *const i8) -> String {
     unsafe { CStr::from_ptr(data).to_string_lossy().into_owned() }
 }
@@ -317,4 +1012,77 @@ mod tests {
             33
         );
     }
+
+    #[test]
+    fn build_context_with_custom_limits() {
+        let ctx = CtxBuilder::new().max_slaves(3).max_groups(4).build();
+        assert_eq!(ctx.slaves().len(), 4);
+        assert_eq!(ctx.groups().len(), 4);
+        assert_eq!(ctx.ecx_ctx.maxslave, 4);
+        assert_eq!(ctx.max_group(), 4);
+        assert_eq!(
+            ctx.ecx_ctx.slavelist,
+            ctx.slave_list.as_ptr() as *mut sys::ec_slave
+        );
+        assert_eq!(
+            ctx.ecx_ctx.grouplist,
+            ctx.group_list.as_ptr() as *mut sys::ec_group
+        );
+
+        let ctx = CtxBuilder::new().max_groups(0).build();
+        assert_eq!(ctx.groups().len(), 1);
+    }
+
+    #[test]
+    fn relocate_process_data_after_resizing_the_io_map() {
+        let mut ctx = Ctx::default();
+        *ctx.slave_count = 2;
+        let base = ctx.io_maps[0].as_mut_ptr();
+        ctx.slave_list[1].0.outputs = base.wrapping_add(0);
+        ctx.slave_list[2].0.outputs = base.wrapping_add(2);
+        ctx.slave_list[2].0.inputs = base.wrapping_add(6);
+        ctx.group_list[0].0.outputs = base;
+        ctx.group_list[0].0.inputs = base.wrapping_add(4);
+
+        ctx.resize_io_map(0, base, 10);
+
+        let base = ctx.io_maps[0].as_mut_ptr();
+        assert_eq!(ctx.io_map(0).len(), 10);
+        assert_eq!(ctx.slave_list[1].0.outputs, base);
+        assert!(ctx.slave_list[1].0.inputs.is_null());
+        assert_eq!(ctx.slave_list[2].0.outputs, base.wrapping_add(2));
+        assert_eq!(ctx.slave_list[2].0.inputs, base.wrapping_add(6));
+        assert_eq!(ctx.group_list[0].0.outputs, base);
+        assert_eq!(ctx.group_list[0].0.inputs, base.wrapping_add(4));
+        assert!(ctx.slave_list[3].0.outputs.is_null());
+    }
+
+    #[test]
+    fn relocate_only_process_data_of_the_group() {
+        let mut ctx = Ctx::default();
+        *ctx.slave_count = 2;
+        let other = ctx.io_maps[0].as_mut_ptr();
+        ctx.slave_list[1].0.outputs = other;
+        ctx.slave_list[2].0.group = 1;
+        let base = ctx.io_maps[1].as_mut_ptr();
+        ctx.slave_list[2].0.outputs = base.wrapping_add(1);
+        ctx.group_list[1].0.outputs = base;
+
+        ctx.resize_io_map(1, base, 4);
+
+        let base = ctx.io_maps[1].as_mut_ptr();
+        assert_eq!(ctx.slave_list[1].0.outputs, other);
+        assert_eq!(ctx.slave_list[2].0.outputs, base.wrapping_add(1));
+        assert_eq!(ctx.group_list[1].0.outputs, base);
+        assert!(ctx.io_map(0).is_empty());
+    }
+
+    #[test]
+    fn reserved_io_map_is_not_reallocated() {
+        let mut ctx = Ctx::default();
+        ctx.reserve_io_map(0, 64);
+        let base = ctx.io_maps[0].as_mut_ptr();
+        ctx.resize_io_map(0, base, 64);
+        assert_eq!(ctx.io_maps[0].as_mut_ptr(), base);
+    }
 }
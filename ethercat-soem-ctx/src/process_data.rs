@@ -0,0 +1,264 @@
+use crate::{Ctx, Error};
+use ethercat_soem_sys as sys;
+use std::{mem::zeroed, ops::Range, ptr, time::Duration};
+
+/// Process data exchange of a single group
+/// that is independent of the [`Ctx`] it was taken from.
+///
+/// It uses its own copy of the SOEM context with a separate
+/// index stack, error list and DC time, so it can exchange the
+/// process data while another thread uses the [`Ctx`] for mailbox
+/// transfers or state requests. Port, slave list and group list
+/// are shared with the [`Ctx`]; SOEM locks the port internally.
+///
+/// The I/O map of the group is owned by this struct.
+#[allow(missing_debug_implementations)]
+pub struct ProcessData {
+    group: u8,
+    overlap: bool,
+    #[allow(dead_code)]
+    e_list: Box<sys::ec_eringt>,
+    #[allow(dead_code)]
+    idx_stack: Box<sys::ec_idxstackT>,
+    #[allow(dead_code)]
+    ecat_error: Box<u8>,
+    dc_time: Box<i64>,
+    ecx_ctx: sys::ecx_context,
+    io_map: Vec<u8>,
+    /// Input and output range of each slave (by slave number) in the I/O map
+    slaves: Vec<(Range<usize>, Range<usize>)>,
+}
+
+// The raw pointers into the `Ctx` are only used by SOEM
+// to send and receive the process data, which is thread-safe
+// as long as each thread uses its own index stack.
+unsafe impl Send for ProcessData {}
+
+impl Ctx {
+    /// Move the I/O map of a group into a separate [`ProcessData`].
+    ///
+    /// The input and output pointers of the slaves of the group are
+    /// cleared, so [`Slave::inputs`](crate::Slave::inputs) and
+    /// [`Slave::outputs`](crate::Slave::outputs) are empty afterwards.
+    /// The group has to be mapped again to use the [`Ctx`]
+    /// for the process data exchange of the group.
+    ///
+    /// # Safety
+    ///
+    /// The returned [`ProcessData`] refers to the port, the slave list
+    /// and the group list of the context, so the context must not be
+    /// dropped or initialised again while the [`ProcessData`] is used.
+    pub unsafe fn take_process_data(&mut self, group: u8, overlap: bool) -> ProcessData {
+        let io_map = std::mem::take(&mut self.io_maps[group as usize]);
+        let base = io_map.as_ptr() as usize;
+        let end = base + io_map.len();
+        // Pointers into the I/O maps of other groups are left untouched.
+        let range = |ptr: *mut u8, len: usize| {
+            let start = ptr as usize;
+            if ptr.is_null() || len == 0 || start < base || start + len > end {
+                return None;
+            }
+            Some(start - base..start - base + len)
+        };
+        let slave_count = self.slave_count();
+        let mut slaves = vec![(0..0, 0..0); slave_count + 1];
+        for (i, slave) in self.slave_list[..=slave_count]
+            .iter_mut()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, s)| group == 0 || s.group() == group)
+        {
+            if let Some(inputs) = range(slave.0.inputs, slave.inputs().len()) {
+                slaves[i].0 = inputs;
+                slave.0.inputs = ptr::null_mut();
+            }
+            if let Some(outputs) = range(slave.0.outputs, slave.outputs().len()) {
+                slaves[i].1 = outputs;
+                slave.0.outputs = ptr::null_mut();
+            }
+        }
+        let mut e_list: Box<sys::ec_eringt> = Box::new(zeroed());
+        let mut idx_stack: Box<sys::ec_idxstackT> = Box::new(zeroed());
+        let mut ecat_error = Box::new(0);
+        let mut dc_time = Box::new(*self.dc_time);
+        let ecx_ctx = sys::ecx_context {
+            elist: &mut *e_list,
+            idxstack: &mut *idx_stack,
+            ecaterror: &mut *ecat_error,
+            DCtime: &mut *dc_time,
+            FOEhook: None,
+            EOEhook: None,
+            userdata: ptr::null_mut(),
+            ..self.ecx_ctx
+        };
+        ProcessData {
+            group,
+            overlap,
+            e_list,
+            idx_stack,
+            ecat_error,
+            dc_time,
+            ecx_ctx,
+            io_map,
+            slaves,
+        }
+    }
+}
+
+impl ProcessData {
+    #[must_use]
+    pub const fn group(&self) -> u8 {
+        self.group
+    }
+    /// Send the process data of the group.
+    ///
+    /// The outputs are sent with LRW datagrams if the group was
+    /// mapped with overlapping inputs and outputs.
+    pub fn send(&mut self) -> i32 {
+        if self.overlap {
+            unsafe { sys::ecx_send_overlap_processdata_group(&mut self.ecx_ctx, self.group) }
+        } else {
+            unsafe { sys::ecx_send_processdata_group(&mut self.ecx_ctx, self.group) }
+        }
+    }
+    /// Receive the process data of the group.
+    ///
+    /// It returns the working counter or `EC_NOFRAME` (= `-1`).
+    pub fn receive(&mut self, timeout: Duration) -> i32 {
+        unsafe {
+            sys::ecx_receive_processdata_group(
+                &mut self.ecx_ctx,
+                self.group,
+                timeout.as_micros() as i32,
+            )
+        }
+    }
+    pub fn is_err(&mut self) -> bool {
+        unsafe { sys::ecx_iserror(&mut self.ecx_ctx) != 0 }
+    }
+    pub fn pop_error(&mut self) -> Option<Error> {
+        let mut ec: sys::ec_errort = unsafe { zeroed() };
+        if unsafe { sys::ecx_poperror(&mut self.ecx_ctx, &mut ec) } != 0 {
+            Some(Error::from(ec))
+        } else {
+            None
+        }
+    }
+    /// DC time of the last exchange
+    #[must_use]
+    pub const fn dc_time(&self) -> i64 {
+        *self.dc_time
+    }
+    #[must_use]
+    pub fn io_map(&self) -> &[u8] {
+        &self.io_map
+    }
+    /// Inputs of a slave (by slave number)
+    ///
+    /// It's empty if the slave is not part of the group.
+    #[must_use]
+    pub fn inputs(&self, slave: u16) -> &[u8] {
+        match self.slaves.get(slave as usize) {
+            Some((inputs, _)) => &self.io_map[inputs.clone()],
+            None => &[],
+        }
+    }
+    /// Outputs of a slave (by slave number)
+    ///
+    /// It's empty if the slave is not part of the group.
+    #[must_use]
+    pub fn outputs(&self, slave: u16) -> &[u8] {
+        match self.slaves.get(slave as usize) {
+            Some((_, outputs)) => &self.io_map[outputs.clone()],
+            None => &[],
+        }
+    }
+    /// Mutable access to the outputs of a slave (by slave number)
+    pub fn outputs_mut(&mut self, slave: u16) -> &mut [u8] {
+        match self.slaves.get(slave as usize) {
+            Some((_, outputs)) => &mut self.io_map[outputs.clone()],
+            None => &mut [],
+        }
+    }
+    /// Returns `true` if the process data of the slave
+    /// (by slave number) is exchanged with this group.
+    #[must_use]
+    pub fn contains(&self, slave: u16) -> bool {
+        self.slaves
+            .get(slave as usize)
+            .map(|(i, o)| !i.is_empty() || !o.is_empty())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_process_data_of_a_group() {
+        let mut ctx = Ctx::default();
+        *ctx.slave_count = 3;
+        ctx.io_maps[1] = vec![1, 2, 3, 4, 5];
+        let base = ctx.io_maps[1].as_mut_ptr();
+        for i in 1..=2 {
+            ctx.slave_list[i].0.group = 1;
+        }
+        ctx.slave_list[1].0.Obytes = 2;
+        ctx.slave_list[1].0.outputs = base;
+        ctx.slave_list[2].0.Ibytes = 3;
+        ctx.slave_list[2].0.inputs = base.wrapping_add(2);
+        ctx.slave_list[3].0.Obytes = 1;
+
+        let mut pd = unsafe { ctx.take_process_data(1, false) };
+
+        assert_eq!(pd.group(), 1);
+        assert_eq!(pd.outputs(1), &[1, 2]);
+        assert_eq!(pd.inputs(1), &[] as &[u8]);
+        assert_eq!(pd.inputs(2), &[3, 4, 5]);
+        assert!(!pd.contains(3));
+        assert!(pd.outputs(7).is_empty());
+        pd.outputs_mut(1)[1] = 9;
+        assert_eq!(pd.io_map(), &[1, 9, 3, 4, 5]);
+
+        assert!(ctx.io_map(1).is_empty());
+        assert!(ctx.slaves()[1].outputs().is_empty());
+        assert!(ctx.slaves()[2].inputs().is_empty());
+        assert_eq!(pd.ecx_ctx.slavelist, ctx.ecx_ctx.slavelist);
+        assert_ne!(pd.ecx_ctx.idxstack, ctx.ecx_ctx.idxstack);
+    }
+
+    #[test]
+    fn take_the_process_data_of_two_groups() {
+        let mut ctx = crate::CtxBuilder::new().max_groups(3).build();
+        *ctx.slave_count = 2;
+        ctx.io_maps[1] = vec![1, 2];
+        ctx.io_maps[2] = vec![3, 4, 5];
+        let base_1 = ctx.io_maps[1].as_mut_ptr();
+        let base_2 = ctx.io_maps[2].as_mut_ptr();
+        ctx.slave_list[1].0.group = 1;
+        ctx.slave_list[1].0.Obytes = 2;
+        ctx.slave_list[1].0.outputs = base_1;
+        ctx.slave_list[2].0.group = 2;
+        ctx.slave_list[2].0.Obytes = 1;
+        ctx.slave_list[2].0.outputs = base_2;
+        ctx.slave_list[2].0.Ibytes = 2;
+        ctx.slave_list[2].0.inputs = base_2.wrapping_add(1);
+
+        // Group 0 is not mapped, so it must not claim any slave.
+        let pd_0 = unsafe { ctx.take_process_data(0, false) };
+        assert!(!pd_0.contains(1));
+        assert!(!pd_0.contains(2));
+        assert_eq!(ctx.slaves()[1].outputs(), &[1, 2]);
+
+        let pd_1 = unsafe { ctx.take_process_data(1, false) };
+        let pd_2 = unsafe { ctx.take_process_data(2, false) };
+        assert_eq!(pd_1.outputs(1), &[1, 2]);
+        assert!(!pd_1.contains(2));
+        assert_eq!(pd_2.outputs(2), &[3]);
+        assert_eq!(pd_2.inputs(2), &[4, 5]);
+        assert!(!pd_2.contains(1));
+        assert!(ctx.slaves()[1].outputs().is_empty());
+        assert!(ctx.slaves()[2].inputs().is_empty());
+    }
+}
@@ -65,6 +65,9 @@ impl Slave {
     }
     /// Imptable access to output data
     pub fn outputs(&self) -> &[u8] {
+        if self.0.outputs.is_null() {
+            return &[];
+        }
         let size = (if self.output_bytes() == 0 && self.output_bits() > 0 {
             1
         } else {
@@ -74,6 +77,9 @@ impl Slave {
     }
     /// Mutable access to output data
     pub fn outputs_mut(&mut self) -> &mut [u8] {
+        if self.0.outputs.is_null() {
+            return &mut [];
+        }
         let size = (if self.output_bytes() == 0 && self.output_bits() > 0 {
             1
         } else {
@@ -97,12 +103,15 @@ impl Slave {
     }
     /// Inputs
     pub fn inputs(&self) -> &[u8] {
+        if self.0.inputs.is_null() {
+            return &[];
+        }
         let size = (if self.input_bytes() == 0 && self.input_bits() > 0 {
             1
         } else {
             self.input_bytes()
         }) as usize;
-        unsafe { slice::from_raw_parts_mut(self.0.inputs, size) }
+        unsafe { slice::from_raw_parts(self.0.inputs, size) }
     }
     /// Startbit in first input byte
     pub const fn input_start_bit(&self) -> u8 {
@@ -227,17 +236,18 @@ impl Slave {
     // previous DC slave
     // DCprevious: uint16
 
-    // TODO:
-    // DC cycle time in ns
-    // DCcycle: int32
-
-    // TODO:
-    // DC shift from clock modulus boundary
-    // DCshift: int32
-
-    // TODO:
-    // DC sync activation, 0=off, 1=on
-    // DCactive: uint8
+    /// DC cycle time
+    pub const fn dc_cycle(&self) -> Duration {
+        Duration::from_nanos(self.0.DCcycle as u64)
+    }
+    /// DC shift from clock modulus boundary in ns
+    pub const fn dc_shift(&self) -> i32 {
+        self.0.DCshift
+    }
+    /// DC sync activation
+    pub const fn dc_active(&self) -> bool {
+        self.0.DCactive != 0
+    }
 
     // TODO:
     // link to config table
@@ -247,13 +257,14 @@ impl Slave {
     // link to SII config
     // SIIindex: uint16
 
-    // TODO:
-    // 1 = 8 bytes per read, 0 = 4 bytes per read
-    // eep_8byte: uint8
-
-    // TODO:
-    // 0 = eeprom to master , 1 = eeprom to PDI
-    // eep_pdi: uint8
+    /// EEPROM reads 8 bytes at once (otherwise 4 bytes)
+    pub const fn eep_8byte(&self) -> bool {
+        self.0.eep_8byte != 0
+    }
+    /// EEPROM is controlled by the PDI (otherwise by the master)
+    pub const fn eep_pdi(&self) -> bool {
+        self.0.eep_pdi != 0
+    }
 
     /// CoE details
     pub const fn coe_details(&self) -> u8 {
@@ -284,6 +295,12 @@ impl Slave {
     pub const fn group(&self) -> u8 {
         self.0.group
     }
+    /// Assign the slave to a group
+    ///
+    /// This has to be done before the group is mapped.
+    pub fn set_group(&mut self, group: u8) {
+        self.0.group = group;
+    }
     /// First unused FMMU
     pub const fn fmmu_unused(&self) -> u8 {
         self.0.FMMUunused
@@ -293,15 +310,19 @@ impl Slave {
     pub const fn is_lost(&self) -> bool {
         self.0.islost != 0
     }
+    /// Mark the slave as (not) responding
+    pub fn set_lost(&mut self, lost: bool) {
+        self.0.islost = if lost { 1 } else { 0 };
+    }
 
     // TODO:
     // registered configuration function PO->SO, (DEPRECATED)
     // PO2SOconfig: Option<unsafe extern "C" fn(slave: uint16) -> c_int>
 
-    // TODO:
-    // registered configuration function PO->SO
-    // PO2SOconfigx:
-    // Option<unsafe extern "C" fn(context: *mut ecx_contextt, slave: uint16) -> c_int>
+    /// Has a registered configuration function PO->SO
+    pub const fn has_config_hook(&self) -> bool {
+        self.0.PO2SOconfigx.is_some()
+    }
 
     /// Readable name
     pub fn name(&self) -> String {
@@ -320,6 +341,8 @@ impl fmt::Debug for Slave {
             .field("eep_man", &self.eep_man())
             .field("eep_id", &self.eep_id())
             .field("eep_rev", &self.eep_rev())
+            .field("eep_8byte", &self.eep_8byte())
+            .field("eep_pdi", &self.eep_pdi())
             .field("interface_type", &self.interface_type())
             .field("device_type", &self.device_type())
             .field("output_bits", &self.output_bits())
@@ -349,6 +372,9 @@ impl fmt::Debug for Slave {
             .field("dc_recv_times_b", &self.dc_recv_times_b())
             .field("dc_recv_times_c", &self.dc_recv_times_c())
             .field("dc_recv_times_d", &self.dc_recv_times_d())
+            .field("dc_cycle", &self.dc_cycle())
+            .field("dc_shift", &self.dc_shift())
+            .field("dc_active", &self.dc_active())
             .field("coe_details", &self.coe_details())
             .field("foe_details", &self.foe_details())
             .field("eoe_details", &self.eoe_details())
@@ -356,6 +382,7 @@ impl fmt::Debug for Slave {
             .field("ebus_current", &self.ebus_current())
             .field("fmmu_unused", &self.fmmu_unused())
             .field("is_lost", &self.is_lost())
+            .field("has_config_hook", &self.has_config_hook())
             .field("name", &self.name())
             .finish()
     }
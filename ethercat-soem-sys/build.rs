@@ -1,9 +1,11 @@
-use std::{env, format, path::PathBuf};
+use std::{
+    env, format, fs, io,
+    path::{Path, PathBuf},
+};
 
 #[cfg(feature = "issue-224-workaround")]
 use std::{
     io::{BufWriter, Write},
-    path::Path,
     process::{Command, Stdio},
 };
 
@@ -16,7 +18,22 @@ fn main() {
     #[cfg(feature = "issue-224-workaround")]
     apply_patch(Path::new(&soem_dir), ISSUE_224_WORKAROUND_PATCH_DATA);
 
-    let dst = cmake::Config::new(&soem_dir).build();
+    println!("cargo:rerun-if-env-changed=EC_MAXSLAVE");
+    println!("cargo:rerun-if-env-changed=SOEM_PATH");
+    println!("cargo:rerun-if-changed={}", soem_dir);
+    println!("cargo:rerun-if-changed=wrapper.h");
+    let build_dir = match env::var("EC_MAXSLAVE") {
+        Ok(max_slave) => {
+            let max_slave: u32 = max_slave
+                .parse()
+                .expect("EC_MAXSLAVE has to be a positive number");
+            let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+            soem_with_max_slave(Path::new(&soem_dir), &out_dir.join("SOEM"), max_slave)
+        }
+        Err(_) => PathBuf::from(&soem_dir),
+    };
+
+    let dst = cmake::Config::new(&build_dir).build();
 
     println!("cargo:rustc-link-search=native={}/lib", dst.display());
     println!("cargo:rustc-link-lib=static=soem");
@@ -56,6 +73,45 @@ fn main() {
     unapply_patch(Path::new(&soem_dir), ISSUE_224_WORKAROUND_PATCH_DATA);
 }
 
+/// Copy the SOEM sources to `dst` and set the maximum number of slaves.
+///
+/// SOEM defines `EC_MAXSLAVE` unconditionally in `ethercatmain.h`,
+/// so it can't be passed as a compiler flag.
+fn soem_with_max_slave(soem_dir: &Path, dst: &Path, max_slave: u32) -> PathBuf {
+    copy_dir(soem_dir, dst).expect("Could not copy the SOEM sources");
+    let header = dst.join("soem").join("ethercatmain.h");
+    let src = fs::read_to_string(&header).expect("Could not read ethercatmain.h");
+    let patched: String = src
+        .lines()
+        .map(|line| {
+            let mut tokens = line.split_whitespace();
+            if tokens.next() == Some("#define") && tokens.next() == Some("EC_MAXSLAVE") {
+                format!("#define EC_MAXSLAVE {}\n", max_slave)
+            } else {
+                format!("{}\n", line)
+            }
+        })
+        .collect();
+    fs::write(&header, patched).expect("Could not write ethercatmain.h");
+    dst.to_path_buf()
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != ".git" {
+                copy_dir(&entry.path(), &target)?;
+            }
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(feature = "issue-224-workaround")]
 fn apply_patch(soem_dir: &Path, patch_data: &[u8]) {
     let mut patch_stdin = Command::new("patch")
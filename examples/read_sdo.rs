@@ -32,35 +32,30 @@ fn read_sdo(ifname: &str) -> Result<()> {
     let cycle_time = Duration::from_micros(5_000);
     let mut read_times = vec![];
 
-    // Dirty hack to use the same underlying SOEM context
-    // within another thread.
-    // usize works independent of the CPU architecture (32/64-bit)
-    let master_ptr = master.ptr();
-    let master_ptr_addr = master_ptr as usize;
+    let (cyclic, mailbox) = master.split();
+    let slave_count = cyclic.slave_count();
 
-    thread::spawn(move || {
-        let mut rt_master = unsafe {
-            let master_ptr = std::mem::transmute(master_ptr_addr);
-            soem::Master::from_ptr(master_ptr)
-        };
-        loop {
-            let cycle_start = Instant::now();
-            rt_master.send_processdata().unwrap();
-            rt_master.recv_processdata().unwrap();
-            for s in rt_master.slaves() {
-                log::debug!("Inputs:{:?}", s.inputs());
-                log::debug!("Outputs:{:?}", s.outputs());
-            }
-            let dt = cycle_start.elapsed();
-            log::debug!("Slave states: {:?}", rt_master.states());
-            match cycle_time.checked_sub(dt) {
-                Some(x) => {
-                    log::debug!("Send & Receive took {}µs", x.as_micros());
-                    thread::sleep(x);
-                }
-                None => {
-                    log::warn!("Send & Receive took {}µs", dt.as_micros());
+    thread::spawn(move || loop {
+        let cycle_start = Instant::now();
+        cyclic.send_processdata().unwrap();
+        cyclic.recv_processdata().unwrap();
+        cyclic
+            .with_group(0, |image| {
+                for i in 0..slave_count {
+                    let slave = ec::SlavePos::from(i as u16);
+                    log::debug!("Inputs:{:?}", image.inputs(slave));
+                    log::debug!("Outputs:{:?}", image.outputs(slave));
                 }
+            })
+            .unwrap();
+        let dt = cycle_start.elapsed();
+        match cycle_time.checked_sub(dt) {
+            Some(x) => {
+                log::debug!("Send & Receive took {}µs", x.as_micros());
+                thread::sleep(x);
+            }
+            None => {
+                log::warn!("Send & Receive took {}µs", dt.as_micros());
             }
         }
     });
@@ -72,7 +67,7 @@ fn read_sdo(ifname: &str) -> Result<()> {
         for idx in &sdo_idxs {
             let sdo_read_start = std::time::Instant::now();
 
-            let data = master.read_sdo_complete(
+            let data = mailbox.read_sdo_complete(
                 ec::SlavePos::from(0),
                 ec::Idx::from(*idx),
                 sdo_read_timeout,
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use ethercat_soem as soem;
 use ethercat_types as ec;
-use std::{thread, time::Duration};
+use std::{ops::ControlFlow, time::Duration};
 
 pub fn main() -> Result<()> {
     env_logger::init();
@@ -42,20 +42,21 @@ fn simple(ifname: &str) -> Result<()> {
         }
     }
 
-    let cycle_time = Duration::from_micros(5_000);
+    let mut runner = soem::CycleRunner::new(Duration::from_micros(5_000));
 
-    for i in 1..=5_000 {
-        let cycle_start = std::time::Instant::now();
-        master.send_processdata()?;
-        let wkc = master.recv_processdata()?;
-        if wkc >= expected_wkc {
-            print!("Processdata cycle {}, WKC {}", i, wkc);
+    runner.run(&mut master, |master, cycle| {
+        if cycle.wkc >= expected_wkc {
+            print!("Processdata cycle {}, WKC {}", cycle.index, cycle.wkc);
             println!(", T:{}", master.dc_time());
         }
-        if let Some(dt) = cycle_time.checked_sub(cycle_start.elapsed()) {
-            thread::sleep(dt);
+        if cycle.index < 5_000 {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
         }
-    }
+    })?;
+
+    log::info!("{:#?}", runner.stats());
 
     Ok(())
 }
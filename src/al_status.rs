@@ -1,178 +1,264 @@
-use num_derive::FromPrimitive;
-use num_traits::cast::FromPrimitive as _;
-
-// TODO: auto generate from `ethercatprint.c`
-
-/// AL status code
-#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
-#[repr(u16)]
-pub enum AlStatus {
-    /// No error
-    NoError = 0x0000,
-
-    /// Unspecified error
-    UnspecifiedError = 0x0001,
-
-    /// No memory,
-    NoMemory = 0x0002,
-
-    /// Invalid requested state change" },
-    InvalidRequestedStateChange = 0x0011,
-
-    /// Unknown requested state,
-    UnknownRequestedState = 0x0012,
-
-    /// Bootstrap not supported
-    BootstrapNotSupported = 0x0013,
-
-    /// No Valid Firmware
-    NoValidFirmware = 0x0014,
+use std::fmt;
+
+/// First code of the vendor specific range
+const VENDOR_SPECIFIC: u16 = 0x8000;
+
+macro_rules! al_status_codes {
+    ($($(#[$attr:meta])* $name:ident = $code:literal => $text:literal,)*) => {
+        /// AL status code
+        ///
+        /// The descriptions follow `ec_ALstatuscode2string` of SOEM
+        /// and ETG.1000.6 for the codes SOEM doesn't know.
+        ///
+        /// The enum can't be cast with `as`,
+        /// use [`AlStatus::code`] or `u16::from` instead.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum AlStatus {
+            $(
+                #[doc = $text]
+                $(#[$attr])*
+                $name,
+            )*
+            /// Vendor specific code (`0x8000`-`0xFFFF`)
+            VendorSpecific(u16),
+            /// Code that isn't defined by ETG.1000
+            Unknown(u16),
+        }
+
+        impl AlStatus {
+            /// The status of a code that is defined by ETG.1000 or vendor specific.
+            ///
+            /// Like the former `FromPrimitive` implementation
+            /// it returns `None` for undefined codes.
+            #[must_use]
+            pub const fn from_u16(code: u16) -> Option<Self> {
+                match code {
+                    $($code => Some(AlStatus::$name),)*
+                    VENDOR_SPECIFIC..=0xFFFF => Some(AlStatus::VendorSpecific(code)),
+                    _ => None,
+                }
+            }
+
+            /// The numeric AL status code.
+            #[must_use]
+            pub const fn code(self) -> u16 {
+                match self {
+                    $(AlStatus::$name => $code,)*
+                    AlStatus::VendorSpecific(code) | AlStatus::Unknown(code) => code,
+                }
+            }
+
+            /// Human readable description of the code.
+            #[must_use]
+            pub const fn description(self) -> &'static str {
+                match self {
+                    $(AlStatus::$name => $text,)*
+                    AlStatus::VendorSpecific(_) => "Vendor specific",
+                    AlStatus::Unknown(_) => "Unknown",
+                }
+            }
+        }
+
+        impl From<u16> for AlStatus {
+            fn from(code: u16) -> Self {
+                AlStatus::from_u16(code).unwrap_or(AlStatus::Unknown(code))
+            }
+        }
+    };
+}
 
-    /// Invalid mailbox configuration
+al_status_codes! {
+    NoError = 0x0000 => "No error",
+    UnspecifiedError = 0x0001 => "Unspecified error",
+    NoMemory = 0x0002 => "No memory",
+    InvalidDeviceSetup = 0x0003 => "Invalid device setup",
+    SiiDoesNotMatchFirmware = 0x0006 => "SII/EEPROM information does not match firmware",
+    FirmwareUpdateFailed = 0x0007 => "Firmware update not successful, old firmware still running",
+    LicenseError = 0x000E => "License error",
+    InvalidRequestedStateChange = 0x0011 => "Invalid requested state change",
+    UnknownRequestedState = 0x0012 => "Unknown requested state",
+    BootstrapNotSupported = 0x0013 => "Bootstrap not supported",
+    NoValidFirmware = 0x0014 => "No valid firmware",
     ///
     /// First value as defined in `ethercatprint.c`.
-    InvalidMailboxConfig = 0x0015,
-
-    /// Invalid mailbox configuration
+    InvalidMailboxConfig = 0x0015 => "Invalid mailbox configuration",
     ///
     /// Second value as defined in `ethercatprint.c`.
-    InvalidMailboxConfig2 = 0x0016,
-
-    /// Invalid sync manager configuration
-    InvalidSyncManagerConfiguration = 0x0017,
-
-    /// No valid inputs available
-    NoValidInputsAvailable = 0x0018,
-
-    /// No valid outputs
-    NoValidOutputs = 0x0019,
-
-    /// Synchronization error
-    SynchronizationError = 0x001A,
-
-    /// Sync manager watchdog
-    SyncManagerWatchdog = 0x001B,
-
-    /// Invalid sync Manager types,
-    InvalidSyncManagerTypes = 0x001C,
-
-    /// Invalid output configuration
-    InvalidOutputConfiguration = 0x001D,
-
-    /// Invalid input configuration
-    InvalidInputConfiguration = 0x001E,
-
-    /// Invalid watchdog configuration
-    InvalidWatchdogConfiguration = 0x001F,
-
-    /// Slave needs cold start
-    SlaveNeedsColdStart = 0x0020,
-
-    /// Slave needs INIT
-    SlaveNeedsInit = 0x0021,
-
-    /// Slave needs PREOP
-    SlaveNeedsPreOp = 0x0022,
-
-    /// Slave needs SAFEOP
-    SlaveNeedsSafeOp = 0x0023,
-
-    /// Invalid input mapping
-    InvalidInputMapping = 0x0024,
-
-    /// Invalid output mapping
-    InvalidOutputMapping = 0x0025,
-
-    /// Inconsistent settings
-    InconsistentSettings = 0x0026,
-
-    /// Freerun not supported
-    FreerunNotSupported = 0x0027,
-
-    /// Synchronisation not supported
-    SynchronisationNotSupported = 0x0028,
-
-    /// Freerun needs 3buffer mode
-    FreerunNeeds3BufferMode = 0x0029,
-
-    /// Background watchdog
-    BackgroundWatchdog = 0x002A,
-
-    /// No valid Inputs and Outputs
-    NovalidInputsAndOutputs = 0x002B,
-
-    /// Fatal sync error
-    FatalSyncError = 0x002C,
-
-    /// No sync error
-    NoSyncError = 0x002D,
-
-    /// Invalid input FMMU configuration
-    InvalidInputFmmuConfiguration = 0x002E,
-
-    /// Invalid DC SYNC configuration
-    InvalidDcSyncConfiguration = 0x0030,
-
-    /// Invalid DC latch configuration
-    InvalidDcLatchConfiguration = 0x0031,
-
-    /// PLL error
-    PllError = 0x0032,
-
-    /// DC sync IO error
-    DcSyncIoError = 0x0033,
-
-    /// DC sync timeout error
-    DcSyncTimeoutError = 0x0034,
-
-    /// DC invalid sync cycle time
-    DcInvalidSyncCycleTime = 0x0035,
-
-    /// DC invalid sync0 cycle time
-    DcInvalidSync0CycleTime = 0x0036,
-
-    /// DC invalid sync1 cycle time
-    DcInvalidSync1CycleTime = 0x0037,
-
-    /// MBX_AOE
-    MbxAoe = 0x0041,
-
-    /// MBX_EOE
-    MbxEoe = 0x0042,
-
-    /// MBX_COE
-    MbxCoe = 0x0043,
-
-    /// MBX_FOE
-    MbxFoe = 0x0044,
-
-    /// MBX_SOE
-    MbxSoe = 0x0045,
-
-    /// MBX_VOE
-    MbcVoe = 0x004F,
-
-    /// EEPROM no access
-    EepromNoAccess = 0x0050,
+    InvalidMailboxConfig2 = 0x0016 => "Invalid mailbox configuration",
+    InvalidSyncManagerConfiguration = 0x0017 => "Invalid sync manager configuration",
+    NoValidInputsAvailable = 0x0018 => "No valid inputs available",
+    NoValidOutputs = 0x0019 => "No valid outputs",
+    SynchronizationError = 0x001A => "Synchronization error",
+    SyncManagerWatchdog = 0x001B => "Sync manager watchdog",
+    InvalidSyncManagerTypes = 0x001C => "Invalid sync Manager types",
+    InvalidOutputConfiguration = 0x001D => "Invalid output configuration",
+    InvalidInputConfiguration = 0x001E => "Invalid input configuration",
+    InvalidWatchdogConfiguration = 0x001F => "Invalid watchdog configuration",
+    SlaveNeedsColdStart = 0x0020 => "Slave needs cold start",
+    SlaveNeedsInit = 0x0021 => "Slave needs INIT",
+    SlaveNeedsPreOp = 0x0022 => "Slave needs PREOP",
+    SlaveNeedsSafeOp = 0x0023 => "Slave needs SAFEOP",
+    InvalidInputMapping = 0x0024 => "Invalid input mapping",
+    InvalidOutputMapping = 0x0025 => "Invalid output mapping",
+    InconsistentSettings = 0x0026 => "Inconsistent settings",
+    FreerunNotSupported = 0x0027 => "Freerun not supported",
+    SynchronisationNotSupported = 0x0028 => "Synchronisation not supported",
+    FreerunNeeds3BufferMode = 0x0029 => "Freerun needs 3buffer mode",
+    BackgroundWatchdog = 0x002A => "Background watchdog",
+    NovalidInputsAndOutputs = 0x002B => "No valid Inputs and Outputs",
+    FatalSyncError = 0x002C => "Fatal sync error",
+    ///
+    /// ETG.1000.6 defines this code as "Invalid output FMMU configuration".
+    NoSyncError = 0x002D => "No sync error",
+    InvalidInputFmmuConfiguration = 0x002E => "Invalid input FMMU configuration",
+    InvalidDcSyncConfiguration = 0x0030 => "Invalid DC SYNC configuration",
+    InvalidDcLatchConfiguration = 0x0031 => "Invalid DC latch configuration",
+    PllError = 0x0032 => "PLL error",
+    DcSyncIoError = 0x0033 => "DC sync IO error",
+    DcSyncTimeoutError = 0x0034 => "DC sync timeout error",
+    DcInvalidSyncCycleTime = 0x0035 => "DC invalid sync cycle time",
+    DcInvalidSync0CycleTime = 0x0036 => "DC invalid sync0 cycle time",
+    DcInvalidSync1CycleTime = 0x0037 => "DC invalid sync1 cycle time",
+    MbxAoe = 0x0041 => "MBX_AOE",
+    MbxEoe = 0x0042 => "MBX_EOE",
+    MbxCoe = 0x0043 => "MBX_COE",
+    MbxFoe = 0x0044 => "MBX_FOE",
+    MbxSoe = 0x0045 => "MBX_SOE",
+    MbcVoe = 0x004F => "MBX_VOE",
+    EepromNoAccess = 0x0050 => "EEPROM no access",
+    EeepromError = 0x0051 => "EEPROM error",
+    ExternalHardwareNotReady = 0x0052 => "External hardware not ready",
+    SlaveRestartedLocally = 0x0060 => "Slave restarted locally",
+    DeviceIdValueUpdated = 0x0061 => "Device identification value updated",
+    ModuleIdentListMismatch = 0x0070 => "Detected module ident list does not match",
+    SupplyVoltageTooLow = 0x0080 => "Supply voltage too low",
+    SupplyVoltageTooHigh = 0x0081 => "Supply voltage too high",
+    TemperatureTooLow = 0x0082 => "Temperature too low",
+    TemperatureTooHigh = 0x0083 => "Temperature too high",
+    ApplicationControllerAvailable = 0x00F0 => "Application controller available",
+}
 
-    /// EEPROM error
-    EeepromError = 0x0051,
+/// How serious an AL status code is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlStatusSeverity {
+    /// No error is pending.
+    None,
+    /// The slave reports a change that doesn't affect the operation.
+    Info,
+    /// A fault during operation (e.g. a watchdog or lost synchronization)
+    /// that is usually cleared by acknowledging it
+    /// and requesting the state again.
+    Warning,
+    /// The configuration or the device is faulty
+    /// and the slave can't reach the requested state without changes.
+    Error,
+}
 
-    /// Slave restarted locally
-    SlaveRestartedLocally = 0x0060,
+impl AlStatus {
+    /// Classify the code.
+    ///
+    /// Vendor specific and unknown codes are treated as errors.
+    #[must_use]
+    pub const fn severity(self) -> AlStatusSeverity {
+        use AlStatus as S;
+        match self {
+            S::NoError => AlStatusSeverity::None,
+            S::SlaveRestartedLocally
+            | S::DeviceIdValueUpdated
+            | S::ApplicationControllerAvailable => AlStatusSeverity::Info,
+            S::NoValidInputsAvailable
+            | S::NoValidOutputs
+            | S::SynchronizationError
+            | S::SyncManagerWatchdog
+            | S::SlaveNeedsInit
+            | S::SlaveNeedsPreOp
+            | S::SlaveNeedsSafeOp
+            | S::BackgroundWatchdog
+            | S::NovalidInputsAndOutputs
+            | S::FatalSyncError
+            | S::NoSyncError
+            | S::PllError
+            | S::DcSyncIoError
+            | S::DcSyncTimeoutError => AlStatusSeverity::Warning,
+            _ => AlStatusSeverity::Error,
+        }
+    }
 
-    /// Device identification value updated
-    DeviceIdValueUpdated = 0x0061,
+    /// The code is in the vendor specific range.
+    #[must_use]
+    pub const fn is_vendor_specific(self) -> bool {
+        matches!(self, AlStatus::VendorSpecific(_))
+    }
+}
 
-    /// Application controller available
-    ApplicationControllerAvailable = 0x00f0,
+impl From<AlStatus> for u16 {
+    fn from(status: AlStatus) -> Self {
+        status.code()
+    }
+}
 
-    /// Unknown
-    Unknown = 0xffff,
+impl fmt::Display for AlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlStatus::VendorSpecific(code) | AlStatus::Unknown(code) => {
+                write!(f, "{} (0x{:04X})", self.description(), code)
+            }
+            _ => f.write_str(self.description()),
+        }
+    }
 }
 
-impl From<u16> for AlStatus {
-    fn from(code: u16) -> Self {
-        AlStatus::from_u16(code).unwrap_or(AlStatus::Unknown)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_codes() {
+        for code in 0..=0xFFFF_u16 {
+            assert_eq!(AlStatus::from(code).code(), code);
+        }
+        assert_eq!(AlStatus::from(0x001B), AlStatus::SyncManagerWatchdog);
+        assert_eq!(AlStatus::from(0x8001), AlStatus::VendorSpecific(0x8001));
+        assert!(AlStatus::from(0xFFFF).is_vendor_specific());
+        assert_eq!(AlStatus::from(0x0004), AlStatus::Unknown(0x0004));
+        assert_eq!(
+            AlStatus::from_u16(0x0011),
+            Some(AlStatus::InvalidRequestedStateChange)
+        );
+        assert_eq!(
+            AlStatus::from_u16(0x8001),
+            Some(AlStatus::VendorSpecific(0x8001))
+        );
+        assert_eq!(AlStatus::from_u16(0x0004), None);
+        assert_eq!(u16::from(AlStatus::NoMemory), 0x0002);
+    }
+
+    #[test]
+    fn display_and_severity() {
+        assert_eq!(AlStatus::from(0x0021).to_string(), "Slave needs INIT");
+        assert_eq!(
+            AlStatus::from(0x8012).to_string(),
+            "Vendor specific (0x8012)"
+        );
+        assert_eq!(AlStatus::from(0x0038).to_string(), "Unknown (0x0038)");
+        assert_eq!(AlStatus::NoError.severity(), AlStatusSeverity::None);
+        assert_eq!(
+            AlStatus::DeviceIdValueUpdated.severity(),
+            AlStatusSeverity::Info
+        );
+        assert_eq!(
+            AlStatus::SyncManagerWatchdog.severity(),
+            AlStatusSeverity::Warning
+        );
+        assert_eq!(
+            AlStatus::InvalidOutputMapping.severity(),
+            AlStatusSeverity::Error
+        );
+        // A power cycle is required.
+        assert_eq!(
+            AlStatus::SlaveNeedsColdStart.severity(),
+            AlStatusSeverity::Error
+        );
+        assert!(AlStatusSeverity::Warning < AlStatusSeverity::Error);
     }
 }
@@ -0,0 +1,232 @@
+use crate::{ctx, ec, DEFAULT_SDO_TIMEOUT, SDO_IDX_PDO_ASSIGN};
+
+/// Identity of a slave type as stored in its EEPROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SlaveIdentity {
+    pub vendor_id: u32,
+    pub product_code: u32,
+    /// If `None`, all revisions match.
+    pub revision: Option<u32>,
+}
+
+impl SlaveIdentity {
+    #[must_use]
+    pub fn of(slave: &ctx::Slave) -> Self {
+        Self {
+            vendor_id: slave.eep_man(),
+            product_code: slave.eep_id(),
+            revision: Some(slave.eep_rev()),
+        }
+    }
+
+    /// Check if the identity of a slave matches.
+    #[must_use]
+    pub fn matches(&self, vendor_id: u32, product_code: u32, revision: u32) -> bool {
+        let revision_matches = match self.revision {
+            Some(r) => r == revision,
+            None => true,
+        };
+        self.vendor_id == vendor_id && self.product_code == product_code && revision_matches
+    }
+}
+
+/// Selects the slaves a configuration applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlaveSelector {
+    /// All slaves with this identity
+    Identity(SlaveIdentity),
+    /// The slave at this position
+    Position(ec::SlavePos),
+    /// The slave with this alias address
+    Alias(u16),
+}
+
+impl SlaveSelector {
+    /// Lower values take precedence.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Position(_) => 0,
+            Self::Alias(_) => 1,
+            Self::Identity(SlaveIdentity {
+                revision: Some(_), ..
+            }) => 2,
+            Self::Identity(_) => 3,
+        }
+    }
+
+    fn matches(&self, pos: ec::SlavePos, slave: &ctx::Slave) -> bool {
+        match self {
+            Self::Identity(id) => id.matches(slave.eep_man(), slave.eep_id(), slave.eep_rev()),
+            Self::Position(p) => *p == pos,
+            Self::Alias(alias) => *alias != 0 && *alias == slave.alias_addr(),
+        }
+    }
+}
+
+/// Find the most specific configuration of a slave.
+///
+/// Position and alias selectors override identity selectors.
+pub(crate) fn select<'a, T>(
+    configs: &'a [(SlaveSelector, T)],
+    pos: ec::SlavePos,
+    slave: &ctx::Slave,
+) -> Option<&'a T> {
+    configs
+        .iter()
+        .filter(|(selector, _)| selector.matches(pos, slave))
+        .min_by_key(|(selector, _)| selector.rank())
+        .map(|(_, cfg)| cfg)
+}
+
+/// PDO configuration of a slave
+///
+/// It's written in PRE-OP before the process data is mapped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlavePdoConfig {
+    pub sms: Vec<SmPdoConfig>,
+}
+
+/// PDOs that are assigned to a sync manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmPdoConfig {
+    pub sm: ec::SmIdx,
+    /// The PDO assignment of the SM can't be changed,
+    /// so only the mapping of its PDOs is written.
+    pub fixed: bool,
+    pub pdos: Vec<PdoConfig>,
+}
+
+/// PDO with an optional custom mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoConfig {
+    pub idx: ec::Idx,
+    /// If `None`, the current mapping of the PDO is used.
+    pub entries: Option<Vec<PdoEntryConfig>>,
+}
+
+/// Object that is mapped into a PDO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdoEntryConfig {
+    pub sdo: ec::SdoIdx,
+    pub bit_len: u8,
+}
+
+impl PdoEntryConfig {
+    fn mapping(&self) -> u32 {
+        let idx = u16::from(self.sdo.idx) as u32;
+        let sub_idx = u8::from(self.sdo.sub_idx) as u32;
+        idx << 16 | sub_idx << 8 | self.bit_len as u32
+    }
+}
+
+/// Write the PDO configuration via SDO.
+///
+/// `slave` is the SOEM slave number (starting with `1`).
+/// On failure it returns the object that could not be written.
+pub(crate) fn write_pdo_config(
+    ctx: &mut ctx::HookCtx<'_>,
+    slave: u16,
+    cfg: &SlavePdoConfig,
+) -> Result<(), ec::SdoIdx> {
+    let mut write = |idx: u16, sub_idx: u8, data: &[u8]| {
+        log::debug!(
+            "Slave {}: write PDO config 0x{:X}.0x{:X}",
+            slave,
+            idx,
+            sub_idx
+        );
+        let wkc = ctx.sdo_write(slave, idx, sub_idx, false, data, DEFAULT_SDO_TIMEOUT);
+        if wkc <= 0 {
+            return Err(ec::SdoIdx::new(idx, sub_idx));
+        }
+        Ok(())
+    };
+    for sm in &cfg.sms {
+        let assign_idx = SDO_IDX_PDO_ASSIGN + u8::from(sm.sm) as u16;
+        if !sm.fixed {
+            write(assign_idx, 0, &[0])?;
+        }
+        for pdo in &sm.pdos {
+            let pdo_idx = u16::from(pdo.idx);
+            if let Some(entries) = &pdo.entries {
+                write(pdo_idx, 0, &[0])?;
+                for (i, e) in entries.iter().enumerate() {
+                    write(pdo_idx, i as u8 + 1, &e.mapping().to_le_bytes())?;
+                }
+                write(pdo_idx, 0, &[entries.len() as u8])?;
+            }
+        }
+        if sm.fixed {
+            continue;
+        }
+        for (i, pdo) in sm.pdos.iter().enumerate() {
+            write(assign_idx, i as u8 + 1, &u16::from(pdo.idx).to_le_bytes())?;
+        }
+        write(assign_idx, 0, &[sm.pdos.len() as u8])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_slave_identity() {
+        let id = SlaveIdentity {
+            vendor_id: 2,
+            product_code: 0x0444_2c52,
+            revision: None,
+        };
+        assert!(id.matches(2, 0x0444_2c52, 0x0011_0000));
+        assert!(!id.matches(2, 0x0444_2c53, 0x0011_0000));
+        let id = SlaveIdentity {
+            revision: Some(0x0010_0000),
+            ..id
+        };
+        assert!(!id.matches(2, 0x0444_2c52, 0x0011_0000));
+        assert!(id.matches(2, 0x0444_2c52, 0x0010_0000));
+    }
+
+    #[test]
+    fn select_most_specific_config() {
+        let slave = ctx::Slave::default();
+        let pos = ec::SlavePos::new(3);
+        let any_rev = SlaveIdentity {
+            vendor_id: 0,
+            product_code: 0,
+            revision: None,
+        };
+        let rev = SlaveIdentity {
+            revision: Some(0),
+            ..any_rev
+        };
+        let mut configs = vec![
+            (SlaveSelector::Identity(any_rev), 1),
+            (SlaveSelector::Position(ec::SlavePos::new(2)), 2),
+        ];
+        assert_eq!(select(&configs, pos, &slave), Some(&1));
+        configs.push((SlaveSelector::Identity(rev), 3));
+        assert_eq!(select(&configs, pos, &slave), Some(&3));
+        // alias 0 means "no alias"
+        configs.push((SlaveSelector::Alias(0), 4));
+        assert_eq!(select(&configs, pos, &slave), Some(&3));
+        configs.push((SlaveSelector::Position(pos), 5));
+        assert_eq!(select(&configs, pos, &slave), Some(&5));
+        assert_eq!(select::<u8>(&[], pos, &slave), None);
+    }
+
+    #[test]
+    fn pdo_entry_mapping() {
+        let e = PdoEntryConfig {
+            sdo: ec::SdoIdx::new(0x6041, 0x00),
+            bit_len: 16,
+        };
+        assert_eq!(e.mapping(), 0x6041_0010);
+        let e = PdoEntryConfig {
+            sdo: ec::SdoIdx::new(0x7010, 0x02),
+            bit_len: 1,
+        };
+        assert_eq!(e.mapping(), 0x7010_0201);
+    }
+}
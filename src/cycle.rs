@@ -0,0 +1,419 @@
+use crate::{DcDriftController, Master, Result};
+use std::{ops::ControlFlow, ptr, time::Duration};
+
+/// Information about the current process data cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// Number of the cycle (starting with `0`)
+    pub index: u64,
+    /// Working counter of the received process data
+    pub wkc: usize,
+    /// Delay between the scheduled deadline and the actual wake-up
+    pub latency: Duration,
+}
+
+/// Min/max/mean of a measured duration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeStats {
+    min: Option<Duration>,
+    max: Duration,
+    sum: Duration,
+    count: u64,
+}
+
+impl TimeStats {
+    fn record(&mut self, d: Duration) {
+        self.min = Some(self.min.map_or(d, |min| min.min(d)));
+        self.max = self.max.max(d);
+        self.sum = self.sum.saturating_add(d);
+        self.count = self.count.saturating_add(1);
+    }
+    #[must_use]
+    pub fn min(&self) -> Duration {
+        self.min.unwrap_or_default()
+    }
+    #[must_use]
+    pub const fn max(&self) -> Duration {
+        self.max
+    }
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::default();
+        }
+        Duration::from_nanos((self.sum.as_nanos() / u128::from(self.count)) as u64)
+    }
+}
+
+/// Statistics of a [`CycleRunner`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CycleStats {
+    cycles: u64,
+    overruns: u64,
+    cycle_time: TimeStats,
+    latency: TimeStats,
+    exec_time: TimeStats,
+}
+
+impl CycleStats {
+    fn record(
+        &mut self,
+        cycle_time: Option<Duration>,
+        latency: Duration,
+        exec_time: Duration,
+        overrun: bool,
+    ) {
+        self.cycles += 1;
+        if overrun {
+            self.overruns += 1;
+        }
+        if let Some(t) = cycle_time {
+            self.cycle_time.record(t);
+        }
+        self.latency.record(latency);
+        self.exec_time.record(exec_time);
+    }
+    /// Number of executed cycles
+    #[must_use]
+    pub const fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    /// Number of cycles that did not finish before the next deadline
+    #[must_use]
+    pub const fn overruns(&self) -> u64 {
+        self.overruns
+    }
+    /// Time between two consecutive wake-ups
+    #[must_use]
+    pub const fn cycle_time(&self) -> &TimeStats {
+        &self.cycle_time
+    }
+    /// Wake-up jitter, i.e. the delay between deadline and actual wake-up
+    #[must_use]
+    pub const fn latency(&self) -> &TimeStats {
+        &self.latency
+    }
+    /// Time needed to exchange the process data and run the callback
+    #[must_use]
+    pub const fn exec_time(&self) -> &TimeStats {
+        &self.exec_time
+    }
+}
+
+/// Drives the process data exchange with a fixed period.
+///
+/// The cycles are scheduled at absolute deadlines on the monotonic clock,
+/// so the execution time of a cycle does not shift the following ones.
+/// With a [`DcDriftController`] the deadlines follow the DC reference clock.
+#[derive(Debug, Clone)]
+pub struct CycleRunner {
+    period: Duration,
+    group: u8,
+    stats: CycleStats,
+    dc_sync: Option<DcDriftController>,
+}
+
+impl CycleRunner {
+    /// # Panics
+    ///
+    /// Panics if the period is zero.
+    #[must_use]
+    pub fn new(period: Duration) -> Self {
+        assert!(period > Duration::ZERO, "the cycle period must not be zero");
+        Self {
+            period,
+            group: 0,
+            stats: CycleStats::default(),
+            dc_sync: None,
+        }
+    }
+
+    /// Synchronize the cycles to the DC reference clock.
+    pub fn set_dc_sync(&mut self, ctrl: Option<DcDriftController>) {
+        self.dc_sync = ctrl;
+    }
+
+    #[must_use]
+    pub const fn dc_sync(&self) -> Option<&DcDriftController> {
+        self.dc_sync.as_ref()
+    }
+
+    #[must_use]
+    pub const fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Exchange only the process data of the given group,
+    /// e.g. to run groups with different cycle times
+    /// in different threads (see [`CyclicHandle`](crate::CyclicHandle)).
+    pub fn set_group(&mut self, group: u8) {
+        self.group = group;
+    }
+
+    #[must_use]
+    pub const fn group(&self) -> u8 {
+        self.group
+    }
+
+    #[must_use]
+    pub const fn stats(&self) -> &CycleStats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = CycleStats::default();
+    }
+
+    /// Send & receive the process data every period and pass
+    /// it to `f` until it returns [`ControlFlow::Break`].
+    pub fn run<F>(&mut self, master: &mut Master, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Master, &Cycle) -> ControlFlow<()>,
+    {
+        let group = self.group;
+        self.run_with(|index, latency| exchange(master, group, index, latency, &mut f))
+    }
+
+    pub(crate) fn run_with<F>(&mut self, step: F) -> Result<()>
+    where
+        F: FnMut(u64, Duration) -> Result<(ControlFlow<()>, i64)>,
+    {
+        self.run_with_clock(&mut MonotonicClock, step)
+    }
+
+    fn run_with_clock<C, F>(&mut self, clock: &mut C, mut step: F) -> Result<()>
+    where
+        C: Clock,
+        F: FnMut(u64, Duration) -> Result<(ControlFlow<()>, i64)>,
+    {
+        let mut deadline = clock.now();
+        let mut last_wakeup = None;
+        for index in 0.. {
+            deadline += self.period;
+            clock.sleep_until(deadline);
+            let wakeup = clock.now();
+            let latency = wakeup.saturating_sub(deadline);
+            let (flow, dc_time) = step(index, latency)?;
+            if let Some(ctrl) = &mut self.dc_sync {
+                let correction = ctrl.update(dc_time, self.period);
+                deadline = shift(deadline, correction);
+            }
+            let done = clock.now();
+            let next_deadline = deadline + self.period;
+            let overrun = done > next_deadline;
+            if overrun {
+                log::debug!(
+                    "Cycle {} overran its deadline by {}µs",
+                    index,
+                    (done - next_deadline).as_micros()
+                );
+                // skip the missed cycles instead of trying to catch up
+                while deadline + self.period < done {
+                    deadline += self.period;
+                }
+            }
+            self.stats.record(
+                last_wakeup.map(|t| wakeup.saturating_sub(t)),
+                latency,
+                done.saturating_sub(wakeup),
+                overrun,
+            );
+            last_wakeup = Some(wakeup);
+            if flow.is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn exchange<F>(
+    master: &mut Master,
+    group: u8,
+    index: u64,
+    latency: Duration,
+    f: &mut F,
+) -> Result<(ControlFlow<()>, i64)>
+where
+    F: FnMut(&mut Master, &Cycle) -> ControlFlow<()>,
+{
+    master.send_group_processdata(group)?;
+    let wkc = master.recv_group_processdata(group)?;
+    let dc_time = master.dc_time();
+    let cycle = Cycle {
+        index,
+        wkc,
+        latency,
+    };
+    Ok((f(master, &cycle), dc_time))
+}
+
+fn shift(t: Duration, ns: i64) -> Duration {
+    let d = Duration::from_nanos(ns.unsigned_abs());
+    if ns < 0 {
+        t.saturating_sub(d)
+    } else {
+        t + d
+    }
+}
+
+/// Time source of the [`CycleRunner`]
+trait Clock {
+    fn now(&mut self) -> Duration;
+    fn sleep_until(&mut self, deadline: Duration);
+}
+
+/// `CLOCK_MONOTONIC` of the OS
+struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&mut self) -> Duration {
+        monotonic_now()
+    }
+    fn sleep_until(&mut self, deadline: Duration) {
+        sleep_until(deadline);
+    }
+}
+
+fn monotonic_now() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+fn sleep_until(deadline: Duration) {
+    let ts = libc::timespec {
+        tv_sec: deadline.as_secs() as libc::time_t,
+        tv_nsec: deadline.subsec_nanos() as libc::c_long,
+    };
+    loop {
+        let res = unsafe {
+            libc::clock_nanosleep(
+                libc::CLOCK_MONOTONIC,
+                libc::TIMER_ABSTIME,
+                &ts,
+                ptr::null_mut(),
+            )
+        };
+        if res != libc::EINTR {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn record_cycle_stats() {
+        let mut stats = CycleStats::default();
+        let us = Duration::from_micros;
+        stats.record(None, us(10), us(100), false);
+        stats.record(Some(us(1_000)), us(30), us(300), false);
+        stats.record(Some(us(1_200)), us(20), us(1_100), true);
+
+        assert_eq!(stats.cycles(), 3);
+        assert_eq!(stats.overruns(), 1);
+
+        assert_eq!(stats.cycle_time().min(), us(1_000));
+        assert_eq!(stats.cycle_time().max(), us(1_200));
+        assert_eq!(stats.cycle_time().mean(), us(1_100));
+
+        assert_eq!(stats.latency().min(), us(10));
+        assert_eq!(stats.latency().max(), us(30));
+        assert_eq!(stats.latency().mean(), us(20));
+
+        assert_eq!(stats.exec_time().min(), us(100));
+        assert_eq!(stats.exec_time().max(), us(1_100));
+        assert_eq!(stats.exec_time().mean(), us(500));
+    }
+
+    /// Clock that only advances while sleeping or when a cycle is executed
+    struct FakeClock {
+        now: Rc<Cell<Duration>>,
+        latency: Duration,
+        deadlines: Vec<Duration>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&mut self) -> Duration {
+            self.now.get()
+        }
+        fn sleep_until(&mut self, deadline: Duration) {
+            self.deadlines.push(deadline);
+            self.now.set(self.now.get().max(deadline) + self.latency);
+        }
+    }
+
+    fn run_cycles(period: Duration, exec_times: &[Duration]) -> (CycleRunner, FakeClock) {
+        let now = Rc::new(Cell::new(Duration::ZERO));
+        let mut clock = FakeClock {
+            now: Rc::clone(&now),
+            latency: Duration::from_micros(10),
+            deadlines: vec![],
+        };
+        let mut runner = CycleRunner::new(period);
+        runner
+            .run_with_clock(&mut clock, |index, latency| {
+                assert_eq!(latency, Duration::from_micros(10));
+                now.set(now.get() + exec_times[index as usize]);
+                let flow = if index as usize + 1 == exec_times.len() {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                };
+                Ok((flow, 0))
+            })
+            .unwrap();
+        (runner, clock)
+    }
+
+    #[test]
+    fn schedule_cycles_at_absolute_deadlines() {
+        let us = Duration::from_micros;
+        let (runner, clock) = run_cycles(us(1_000), &[us(100), us(300), us(200)]);
+
+        assert_eq!(clock.deadlines, vec![us(1_000), us(2_000), us(3_000)]);
+        let stats = runner.stats();
+        assert_eq!(stats.cycles(), 3);
+        assert_eq!(stats.overruns(), 0);
+        assert_eq!(stats.cycle_time().mean(), us(1_000));
+        assert_eq!(stats.latency().mean(), us(10));
+        assert_eq!(stats.exec_time().max(), us(300));
+    }
+
+    #[test]
+    fn skip_missed_cycles_after_an_overrun() {
+        let us = Duration::from_micros;
+        let (runner, clock) = run_cycles(us(1_000), &[us(100), us(2_500), us(100)]);
+
+        // The second cycle ends at 4.51ms, so the
+        // deadlines at 3ms and 4ms are skipped.
+        assert_eq!(clock.deadlines, vec![us(1_000), us(2_000), us(5_000)]);
+        let stats = runner.stats();
+        assert_eq!(stats.cycles(), 3);
+        assert_eq!(stats.overruns(), 1);
+        assert_eq!(stats.cycle_time().max(), us(3_000));
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_a_zero_period() {
+        let _ = CycleRunner::new(Duration::ZERO);
+    }
+
+    #[test]
+    fn empty_time_stats() {
+        let stats = TimeStats::default();
+        assert_eq!(stats.min(), Duration::ZERO);
+        assert_eq!(stats.max(), Duration::ZERO);
+        assert_eq!(stats.mean(), Duration::ZERO);
+    }
+}
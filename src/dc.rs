@@ -0,0 +1,280 @@
+use crate::{ec, Error, Master, Result};
+use std::{convert::TryFrom, time::Duration};
+
+const DEFAULT_SYNC_OFFSET: Duration = Duration::from_micros(50);
+const DEFAULT_KP: f64 = 0.01;
+const DEFAULT_KI: f64 = 0.05;
+
+/// PI controller that aligns the cycles of the master
+/// to the DC reference clock.
+///
+/// The host clock drifts against the reference clock,
+/// so the master has to correct its cycle deadlines to keep
+/// a constant phase to the SYNC signals of the slaves.
+/// This is a port of `ec_sync` from SOEM's `red_test.c`:
+/// the controller measures the offset of the DC time
+/// modulo the cycle period and shifts the next deadline.
+/// Like in SOEM the integral part counts the sign of the offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcDriftController {
+    sync_offset: Duration,
+    kp: f64,
+    ki: f64,
+    offset: i64,
+    integral: i64,
+    correction: i64,
+}
+
+impl Default for DcDriftController {
+    fn default() -> Self {
+        Self::new(DEFAULT_SYNC_OFFSET, DEFAULT_KP, DEFAULT_KI)
+    }
+}
+
+impl DcDriftController {
+    /// Create a controller that wakes up the master `sync_offset`
+    /// after the DC cycle boundary.
+    #[must_use]
+    pub const fn new(sync_offset: Duration, kp: f64, ki: f64) -> Self {
+        Self {
+            sync_offset,
+            kp,
+            ki,
+            offset: 0,
+            integral: 0,
+            correction: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn sync_offset(&self) -> Duration {
+        self.sync_offset
+    }
+
+    /// Proportional and integral gain
+    #[must_use]
+    pub const fn gains(&self) -> (f64, f64) {
+        (self.kp, self.ki)
+    }
+
+    pub fn set_gains(&mut self, kp: f64, ki: f64) {
+        self.kp = kp;
+        self.ki = ki;
+    }
+
+    /// Last measured offset to the DC cycle in ns
+    #[must_use]
+    pub const fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Integral part of the controller
+    #[must_use]
+    pub const fn integral(&self) -> i64 {
+        self.integral
+    }
+
+    /// Last correction of the cycle deadline in ns
+    #[must_use]
+    pub const fn correction(&self) -> i64 {
+        self.correction
+    }
+
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.integral = 0;
+        self.correction = 0;
+    }
+
+    /// Calculate the correction (in ns) of the next cycle deadline.
+    ///
+    /// SOEM reports a DC time of `0` if no DC slave is present,
+    /// so no correction is done in that case.
+    pub fn update(&mut self, dc_time: i64, period: Duration) -> i64 {
+        let period = period.as_nanos() as i64;
+        if period == 0 || dc_time == 0 {
+            return 0;
+        }
+        let mut offset = (dc_time - self.sync_offset.as_nanos() as i64) % period;
+        if offset > period / 2 {
+            offset -= period;
+        } else if offset < -(period / 2) {
+            offset += period;
+        }
+        self.offset = offset;
+        self.integral += offset.signum();
+        self.correction = -(self.kp * offset as f64 + self.ki * self.integral as f64) as i64;
+        self.correction
+    }
+}
+
+/// SYNC signal settings of a DC slave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DcSync {
+    pub cycle_time: Duration,
+    /// If `Some`, SYNC1 is activated and fires this offset after SYNC0.
+    pub sync1_offset: Option<Duration>,
+    /// Shift of SYNC0 from the clock modulus boundary in ns
+    ///
+    /// A negative shift fires SYNC0 before the boundary.
+    pub shift: i32,
+}
+
+impl Master {
+    /// Set the SYNC signals of a DC slave
+    /// that are activated by [`Master::config_map`].
+    pub fn set_dc_sync(&mut self, slave: ec::SlavePos, sync: DcSync) {
+        self.dc_syncs.insert(u16::from(slave), sync);
+    }
+
+    pub(crate) fn activate_dc_syncs(&mut self) -> Result<()> {
+        let mut syncs: Vec<_> = self.dc_syncs.iter().map(|(s, d)| (*s, *d)).collect();
+        syncs.sort_unstable_by_key(|(slave, _)| *slave);
+        for (slave, sync) in syncs {
+            let slave = ec::SlavePos::new(slave);
+            match sync.sync1_offset {
+                Some(offset) => self.dc_sync01(slave, sync.cycle_time, offset, sync.shift)?,
+                None => self.dc_sync0(slave, sync.cycle_time, sync.shift)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Activate SYNC0 of a DC slave.
+    ///
+    /// SYNC0 fires every `cycle_time`, shifted by `shift` (in ns)
+    /// from the clock modulus boundary.
+    pub fn dc_sync0(
+        &mut self,
+        slave: ec::SlavePos,
+        cycle_time: Duration,
+        shift: i32,
+    ) -> Result<()> {
+        let pos = self.dc_slave(slave)?;
+        let cycle_time = nanos(cycle_time)?;
+        log::debug!(
+            "Activate SYNC0 of {:?}: cycle time {}ns, shift {}ns",
+            slave,
+            cycle_time,
+            shift
+        );
+        self.ctx.dc_sync0(pos, true, cycle_time, shift);
+        Ok(())
+    }
+
+    /// Activate SYNC0 and SYNC1 of a DC slave.
+    ///
+    /// SYNC0 fires every `cycle_time`, shifted by `shift` (in ns)
+    /// from the clock modulus boundary.
+    /// SYNC1 fires `sync1_offset` after SYNC0.
+    pub fn dc_sync01(
+        &mut self,
+        slave: ec::SlavePos,
+        cycle_time: Duration,
+        sync1_offset: Duration,
+        shift: i32,
+    ) -> Result<()> {
+        let pos = self.dc_slave(slave)?;
+        let cycle_time = nanos(cycle_time)?;
+        let sync1_offset = nanos(sync1_offset)?;
+        log::debug!(
+            "Activate SYNC0/SYNC1 of {:?}: cycle time {}ns, SYNC1 offset {}ns, shift {}ns",
+            slave,
+            cycle_time,
+            sync1_offset,
+            shift
+        );
+        self.ctx
+            .dc_sync01(pos, true, cycle_time, sync1_offset, shift);
+        Ok(())
+    }
+
+    /// Deactivate the SYNC signals of a DC slave.
+    pub fn dc_sync_off(&mut self, slave: ec::SlavePos) -> Result<()> {
+        let pos = self.dc_slave(slave)?;
+        log::debug!("Deactivate SYNC signals of {:?}", slave);
+        self.ctx.dc_sync0(pos, false, 0, 0);
+        Ok(())
+    }
+
+    fn dc_slave(&self, slave: ec::SlavePos) -> Result<u16> {
+        let s = self
+            .slaves()
+            .get(usize::from(slave))
+            .ok_or(Error::SlaveNotFound(slave))?;
+        if !s.has_dc() {
+            return Err(Error::NoDc(slave));
+        }
+        Ok(u16::from(slave) + 1)
+    }
+}
+
+/// SOEM expects the DC times as 32 bit nanoseconds.
+/// Like the signed shift the times are limited to ~2.14s.
+fn nanos(d: Duration) -> Result<u32> {
+    u32::try_from(d.as_nanos())
+        .ok()
+        .filter(|ns| *ns <= i32::MAX as u32)
+        .ok_or(Error::DcTime(d))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dc_time_in_nanos() {
+        assert_eq!(nanos(Duration::from_micros(1_000)).unwrap(), 1_000_000);
+        assert_eq!(nanos(Duration::ZERO).unwrap(), 0);
+        assert!(nanos(Duration::from_secs(2)).is_ok());
+        assert!(matches!(
+            nanos(Duration::from_secs(3)),
+            Err(Error::DcTime(_))
+        ));
+    }
+
+    #[test]
+    fn drift_controller_like_ec_sync() {
+        let period = Duration::from_millis(1);
+        let mut ctrl = DcDriftController::default();
+
+        // 300ns behind the sync point
+        let dc_time = 1_000_000_000 + 50_000 + 300;
+        assert_eq!(ctrl.update(dc_time, period), -3);
+        assert_eq!(ctrl.offset(), 300);
+        assert_eq!(ctrl.integral(), 1);
+
+        // 200ns ahead of the sync point
+        let dc_time = 1_000_000_000 + 50_000 - 200;
+        assert_eq!(ctrl.update(dc_time, period), 2);
+        assert_eq!(ctrl.offset(), -200);
+        assert_eq!(ctrl.integral(), 0);
+
+        for _ in 0..40 {
+            ctrl.update(1_000_000_000 + 50_000 + 10, period);
+        }
+        assert_eq!(ctrl.integral(), 40);
+        assert_eq!(ctrl.correction(), -2);
+
+        // no DC slave
+        assert_eq!(ctrl.update(0, period), 0);
+        assert_eq!(ctrl.integral(), 40);
+
+        ctrl.reset();
+        assert_eq!(ctrl.integral(), 0);
+    }
+
+    #[test]
+    fn wrap_offsets_before_the_sync_offset() {
+        let period = Duration::from_millis(1);
+        let mut ctrl = DcDriftController::new(Duration::from_micros(800), DEFAULT_KP, 0.0);
+
+        // 300µs behind the sync point of the previous cycle
+        ctrl.update(100_000, period);
+        assert_eq!(ctrl.offset(), 300_000);
+
+        // 300µs ahead of the sync point
+        ctrl.update(1_500_000, period);
+        assert_eq!(ctrl.offset(), -300_000);
+    }
+}
@@ -0,0 +1,260 @@
+//! CoE emergency messages
+//!
+//! SOEM receives emergencies with any mailbox transfer
+//! and stores them in its error list, where they are collected
+//! by [`Master::poll_emergencies`] (or when an error is evaluated).
+//! [`Master::poll_emergencies`] also checks the mailboxes
+//! of the CoE slaves, so emergencies arrive without other transfers.
+
+use crate::{ctx, ec, Master, MBX_PROTO_COE};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+/// Number of emergencies that are kept per slave
+const EMERGENCY_HISTORY_LEN: usize = 16;
+
+/// Emergency message of a slave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emergency {
+    pub slave: ec::SlavePos,
+    pub error_code: u16,
+    pub error_register: u8,
+    /// Manufacturer specific data
+    pub data: [u8; 5],
+}
+
+impl Emergency {
+    /// Description of the error code (see [`error_code_description`]).
+    #[must_use]
+    pub fn description(&self) -> &'static str {
+        error_code_description(self.error_code)
+    }
+}
+
+impl fmt::Display for Emergency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: emergency 0x{:04X} ({}), error register 0x{:02X}, data {:02X?}",
+            self.slave,
+            self.error_code,
+            self.description(),
+            self.error_register,
+            self.data
+        )
+    }
+}
+
+/// Standard error codes of CiA 301, CiA 402 and EtherCAT
+const ERROR_CODES: &[(u16, &str)] = &[
+    (0x0000, "Error reset or no error"),
+    (0x1000, "Generic error"),
+    (0x2000, "Current"),
+    (0x2100, "Current, device input side"),
+    (0x2200, "Current inside the device"),
+    (0x2300, "Current, device output side"),
+    (0x2310, "Continuous over current"),
+    (0x2320, "Short circuit or earth leakage"),
+    (0x2330, "Earth leakage"),
+    (0x3000, "Voltage"),
+    (0x3100, "Mains voltage"),
+    (0x3110, "Mains over-voltage"),
+    (0x3120, "Mains under-voltage"),
+    (0x3130, "Phase failure"),
+    (0x3200, "Voltage inside the device"),
+    (0x3210, "DC link over-voltage"),
+    (0x3220, "DC link under-voltage"),
+    (0x3300, "Output voltage"),
+    (0x4000, "Temperature"),
+    (0x4100, "Ambient temperature"),
+    (0x4200, "Device temperature"),
+    (0x4210, "Excess temperature device"),
+    (0x4300, "Drive temperature"),
+    (0x4310, "Excess temperature drive"),
+    (0x5000, "Device hardware"),
+    (0x6000, "Device software"),
+    (0x6100, "Internal software"),
+    (0x6200, "User software"),
+    (0x6300, "Data set"),
+    (0x7000, "Additional modules"),
+    (0x7100, "Power"),
+    (0x7121, "Motor blocked"),
+    (0x7300, "Sensor"),
+    (0x8000, "Monitoring"),
+    (0x8100, "Communication"),
+    (0x8110, "CAN overrun"),
+    (0x8120, "CAN in error passive mode"),
+    (0x8130, "Life guard error or heartbeat error"),
+    (0x8140, "Recovered from bus off"),
+    (0x8200, "Protocol error"),
+    (0x8210, "PDO not processed due to length error"),
+    (0x8220, "PDO length exceeded"),
+    (0x8600, "Positioning controller"),
+    (0x8611, "Following error"),
+    (0x8612, "Reference limit"),
+    (0x9000, "External error"),
+    (
+        0xA000,
+        "Transition from PRE-OP to SAFE-OP was not successful",
+    ),
+    (0xA001, "Transition from SAFE-OP to OP was not successful"),
+    (0xF000, "Additional functions"),
+    (0xFF00, "Device specific"),
+];
+
+/// Describe an emergency error code.
+///
+/// Codes without an entry are described by their group,
+/// e.g. `0x4211` is a "Excess temperature device" error.
+#[must_use]
+pub fn error_code_description(code: u16) -> &'static str {
+    [code, code & 0xFFF0, code & 0xFF00, code & 0xF000]
+        .iter()
+        .find_map(|c| {
+            ERROR_CODES
+                .iter()
+                .find(|(x, _)| x == c)
+                .map(|(_, text)| *text)
+        })
+        .unwrap_or("Unknown error")
+}
+
+/// History and subscribers of the emergencies
+#[derive(Debug, Default)]
+pub(crate) struct Emergencies {
+    history: HashMap<ec::SlavePos, VecDeque<Emergency>>,
+    subscribers: Vec<Sender<Emergency>>,
+}
+
+impl Emergencies {
+    fn record(&mut self, emergency: Emergency) {
+        log::warn!("{}", emergency);
+        self.subscribers
+            .retain(|tx| tx.send(emergency.clone()).is_ok());
+        let history = self.history.entry(emergency.slave).or_default();
+        if history.len() >= EMERGENCY_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(emergency);
+    }
+}
+
+impl Master {
+    /// Check the mailbox of each CoE slave for an emergency
+    /// and collect the emergencies that were received
+    /// with the previous mailbox transfers.
+    ///
+    /// The mailboxes are read without waiting. The errors are collected
+    /// after each slave, so the error list of SOEM (`EC_MAXELIST` entries)
+    /// doesn't overflow.
+    pub fn poll_emergencies(&mut self) {
+        self.collect_emergencies();
+        for i in 0..self.slave_count() {
+            if self.slaves()[i].mbx_proto() & MBX_PROTO_COE == 0 {
+                continue;
+            }
+            self.ctx.mbx_receive(i as u16 + 1, Duration::ZERO);
+            self.collect_emergencies();
+        }
+    }
+
+    fn collect_emergencies(&mut self) {
+        if !self.ctx.is_err() {
+            return;
+        }
+        let errors: Vec<_> = self
+            .ctx_errors()
+            .into_iter()
+            .filter(|e| e.err_type != ctx::ErrType::Emergency)
+            .collect();
+        if !errors.is_empty() {
+            log::debug!("Context errors: {:?}", errors);
+        }
+    }
+
+    /// Subscribe to the emergencies of all slaves.
+    ///
+    /// The receiver gets all emergencies that are collected afterwards.
+    pub fn subscribe_emergencies(&mut self) -> Receiver<Emergency> {
+        let (tx, rx) = mpsc::channel();
+        self.emergencies.subscribers.push(tx);
+        rx
+    }
+
+    /// The last emergencies of a slave (oldest first).
+    #[must_use]
+    pub fn emergency_history(&self, slave: ec::SlavePos) -> Vec<Emergency> {
+        self.emergencies
+            .history
+            .get(&slave)
+            .map(|h| h.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear_emergency_history(&mut self, slave: ec::SlavePos) {
+        self.emergencies.history.remove(&slave);
+    }
+
+    pub(crate) fn record_emergency(&mut self, error: &ctx::Error) {
+        if let Some(code) = error.emergency {
+            self.emergencies.record(Emergency {
+                slave: ec::SlavePos::new(error.slave.saturating_sub(1)),
+                error_code: code.error_code,
+                error_register: code.error_register,
+                data: code.data,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emergency(slave: u16, error_code: u16) -> Emergency {
+        Emergency {
+            slave: ec::SlavePos::new(slave),
+            error_code,
+            error_register: 0x01,
+            data: [0; 5],
+        }
+    }
+
+    #[test]
+    fn describe_error_codes() {
+        assert_eq!(error_code_description(0x8611), "Following error");
+        assert_eq!(error_code_description(0x4211), "Excess temperature device");
+        assert_eq!(error_code_description(0x3399), "Output voltage");
+        assert_eq!(error_code_description(0x5123), "Device hardware");
+        assert_eq!(error_code_description(0xFF42), "Device specific");
+        assert_eq!(error_code_description(0xB000), "Unknown error");
+    }
+
+    #[test]
+    fn keep_a_bounded_history_per_slave() {
+        let mut emergencies = Emergencies::default();
+        let rx = {
+            let (tx, rx) = mpsc::channel();
+            emergencies.subscribers.push(tx);
+            rx
+        };
+        for i in 0..=EMERGENCY_HISTORY_LEN as u16 {
+            emergencies.record(emergency(0, 0x1000 + i));
+        }
+        emergencies.record(emergency(1, 0x8611));
+
+        let history = &emergencies.history[&ec::SlavePos::new(0)];
+        assert_eq!(history.len(), EMERGENCY_HISTORY_LEN);
+        assert_eq!(history[0].error_code, 0x1001);
+        assert_eq!(emergencies.history[&ec::SlavePos::new(1)].len(), 1);
+        assert_eq!(rx.try_iter().count(), EMERGENCY_HISTORY_LEN + 2);
+
+        drop(rx);
+        emergencies.record(emergency(1, 0x8611));
+        assert!(emergencies.subscribers.is_empty());
+    }
+}
@@ -0,0 +1,426 @@
+//! Import of EtherCAT Network Information (ENI) files
+//!
+//! An ENI file describes the whole network as it was set up
+//! in a configuration tool. Only the parts that the master supports
+//! are imported:
+//!
+//! - the expected slaves (identity at each position)
+//! - the PDO assignment and mapping of CoE slaves
+//! - CoE init commands of the PRE-OP to SAFE-OP transition
+//! - the DC SYNC settings
+
+use crate::{
+    ec,
+    xml::{
+        child, children, flag, i32_value, invalid, number, text, u16_value, u32_value, u8_value,
+        value, Result, XmlError,
+    },
+    DcSync, ExpectedSlave, Master, PdoConfig, PdoEntryConfig, SlavePdoConfig, SlaveSelector,
+    SmPdoConfig, StartupSdo,
+};
+use roxmltree::Node;
+use std::{convert::TryFrom, fs, path::Path, time::Duration};
+
+/// CoE command specifier of an SDO download
+const CCS_DOWNLOAD: u64 = 1;
+
+/// Network configuration of an ENI file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eni {
+    /// Slaves ordered by position
+    pub slaves: Vec<EniSlave>,
+}
+
+/// Configuration of a single slave
+#[derive(Debug, Clone, PartialEq)]
+pub struct EniSlave {
+    pub name: String,
+    pub expected: ExpectedSlave,
+    /// `None` if the slave has no CoE mailbox.
+    pub pdo_config: Option<SlavePdoConfig>,
+    /// Init commands are not read back because
+    /// they can contain write-only objects.
+    pub startup_sdos: Vec<StartupSdo>,
+    pub dc_sync: Option<DcSync>,
+}
+
+impl Eni {
+    /// Read an ENI file.
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let xml = fs::read_to_string(path)?;
+        Self::parse(&xml)
+    }
+
+    /// Parse the XML of an ENI file.
+    pub fn parse(xml: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(xml)?;
+        let config =
+            child(doc.root_element(), "Config").ok_or(XmlError::MissingElement("Config"))?;
+        let slaves = children(config, "Slave")
+            .map(parse_slave)
+            .collect::<Result<_>>()?;
+        Ok(Self { slaves })
+    }
+}
+
+impl Master {
+    /// Configure the master like described by an ENI file.
+    ///
+    /// This sets the expected topology, the PDO configurations,
+    /// the startup SDOs and the DC SYNC signals of all slaves,
+    /// so it has to be called before [`Master::auto_config`].
+    pub fn apply_eni(&mut self, eni: &Eni) {
+        self.set_expected_topology(eni.slaves.iter().map(|s| s.expected.clone()).collect());
+        for (i, slave) in eni.slaves.iter().enumerate() {
+            let pos = ec::SlavePos::new(i as u16);
+            if let Some(cfg) = &slave.pdo_config {
+                self.set_pdo_config(pos, cfg.clone());
+            }
+            if !slave.startup_sdos.is_empty() {
+                self.add_startup_sdos(SlaveSelector::Position(pos), slave.startup_sdos.clone());
+            }
+            if let Some(sync) = slave.dc_sync {
+                self.set_dc_sync(pos, sync);
+            }
+        }
+    }
+}
+
+fn parse_slave(node: Node<'_, '_>) -> Result<EniSlave> {
+    let info = child(node, "Info").ok_or(XmlError::MissingElement("Info"))?;
+    let name = child(info, "Name")
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .to_string();
+    let revision = u32_value(info, "RevisionNo")?;
+    let expected = ExpectedSlave {
+        vendor_id: u32_value(info, "VendorId")?,
+        product_code: u32_value(info, "ProductCode")?,
+        revision: Some(revision..=revision),
+        alias: None,
+    };
+    let coe = child(node, "Mailbox").and_then(|m| child(m, "CoE"));
+    let pdo_config = match (coe, child(node, "ProcessData")) {
+        (Some(_), Some(process_data)) => Some(parse_pdo_config(process_data)?),
+        (Some(_), None) => Some(SlavePdoConfig::default()),
+        (None, _) => None,
+    };
+    let startup_sdos = match coe.and_then(|c| child(c, "InitCmds")) {
+        Some(cmds) => parse_init_cmds(&name, cmds)?,
+        None => vec![],
+    };
+    let dc_sync = match child(node, "DC") {
+        Some(dc) => parse_dc(dc)?,
+        None => None,
+    };
+    Ok(EniSlave {
+        name,
+        expected,
+        pdo_config,
+        startup_sdos,
+        dc_sync,
+    })
+}
+
+fn parse_pdo_config(process_data: Node<'_, '_>) -> Result<SlavePdoConfig> {
+    let mut sms: Vec<SmPdoConfig> = vec![];
+    let pdos = process_data
+        .children()
+        .filter(|n| n.has_tag_name("RxPdo") || n.has_tag_name("TxPdo"));
+    for pdo in pdos {
+        // PDOs without SM are not assigned.
+        let sm = match pdo.attribute("Sm") {
+            Some(sm) => u8::try_from(number("Sm", sm)?).map_err(|_| invalid("Sm", sm))?,
+            None => continue,
+        };
+        let fixed = flag(pdo.attribute("Fixed"));
+        let entries = if fixed {
+            None
+        } else {
+            Some(
+                children(pdo, "Entry")
+                    .map(parse_pdo_entry)
+                    .collect::<Result<_>>()?,
+            )
+        };
+        let cfg = PdoConfig {
+            idx: ec::Idx::new(u16_value(pdo, "Index")?),
+            entries,
+        };
+        let sm = ec::SmIdx::new(sm);
+        match sms.iter_mut().find(|s| s.sm == sm) {
+            Some(s) => {
+                s.fixed &= fixed;
+                s.pdos.push(cfg);
+            }
+            None => sms.push(SmPdoConfig {
+                sm,
+                fixed,
+                pdos: vec![cfg],
+            }),
+        }
+    }
+    sms.sort_by_key(|s| u8::from(s.sm));
+    Ok(SlavePdoConfig { sms })
+}
+
+fn parse_pdo_entry(entry: Node<'_, '_>) -> Result<PdoEntryConfig> {
+    let idx = u16_value(entry, "Index")?;
+    // Gaps have no subindex.
+    let sub_idx = match child(entry, "SubIndex") {
+        Some(_) => u8_value(entry, "SubIndex")?,
+        None => 0,
+    };
+    Ok(PdoEntryConfig {
+        sdo: ec::SdoIdx::new(idx, sub_idx),
+        bit_len: u8_value(entry, "BitLen")?,
+    })
+}
+
+fn parse_init_cmds(slave: &str, cmds: Node<'_, '_>) -> Result<Vec<StartupSdo>> {
+    let mut sdos = vec![];
+    for cmd in children(cmds, "InitCmd") {
+        let pre_op_to_safe_op =
+            children(cmd, "Transition").any(|t| t.text().map(str::trim) == Some("PS"));
+        if !pre_op_to_safe_op || value(cmd, "Ccs")? != CCS_DOWNLOAD {
+            continue;
+        }
+        let idx = u16_value(cmd, "Index")?;
+        let sub_idx = u8_value(cmd, "SubIndex")?;
+        if is_pdo_object(idx) {
+            // The PDO configuration is taken from the process data description.
+            continue;
+        }
+        if flag(cmd.attribute("CompleteAccess")) {
+            log::warn!(
+                "{}: skip init command for 0x{:X}: complete access is not supported",
+                slave,
+                idx
+            );
+            continue;
+        }
+        let data = text(cmd, "Data")?;
+        let data = hex_bytes(data).ok_or_else(|| invalid("Data", data))?;
+        sdos.push(StartupSdo {
+            idx: ec::SdoIdx::new(idx, sub_idx),
+            value: ec::Value::Raw(data),
+            verify: false,
+        });
+    }
+    Ok(sdos)
+}
+
+fn parse_dc(dc: Node<'_, '_>) -> Result<Option<DcSync>> {
+    let cycle_time = match child(dc, "CycleTime0") {
+        Some(_) => value(dc, "CycleTime0")?,
+        None => return Ok(None),
+    };
+    if cycle_time == 0 {
+        return Ok(None);
+    }
+    let sync1_offset = match child(dc, "CycleTime1") {
+        Some(_) => Some(value(dc, "CycleTime1")?).filter(|t| *t > 0),
+        None => None,
+    };
+    let shift = match child(dc, "ShiftTime") {
+        Some(_) => i32_value(dc, "ShiftTime")?,
+        None => 0,
+    };
+    Ok(Some(DcSync {
+        cycle_time: Duration::from_nanos(cycle_time),
+        sync1_offset: sync1_offset.map(Duration::from_nanos),
+        shift,
+    }))
+}
+
+const fn is_pdo_object(idx: u16) -> bool {
+    matches!(idx, 0x1600..=0x1BFF | 0x1C10..=0x1C2F)
+}
+
+fn hex_bytes(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENI: &str = r#"<?xml version="1.0"?>
+<EtherCATConfig>
+  <Config>
+    <Master><Info><Name>Master</Name></Info></Master>
+    <Slave>
+      <Info>
+        <Name>Term 1 (EK1100)</Name>
+        <VendorId>2</VendorId>
+        <ProductCode>#x044c2c52</ProductCode>
+        <RevisionNo>#x00110000</RevisionNo>
+      </Info>
+    </Slave>
+    <Slave>
+      <Info>
+        <Name>Drive</Name>
+        <VendorId>#x0000009a</VendorId>
+        <ProductCode>#x00030924</ProductCode>
+        <RevisionNo>#x00010420</RevisionNo>
+      </Info>
+      <ProcessData>
+        <RxPdo Sm="2">
+          <Index>#x1600</Index>
+          <Entry><Index>#x6040</Index><SubIndex>0</SubIndex><BitLen>16</BitLen></Entry>
+          <Entry><Index>#x0</Index><BitLen>16</BitLen></Entry>
+        </RxPdo>
+        <RxPdo><Index>#x1601</Index></RxPdo>
+        <TxPdo Sm="3" Fixed="1"><Index>#x1a00</Index></TxPdo>
+      </ProcessData>
+      <Mailbox>
+        <CoE>
+          <InitCmds>
+            <InitCmd>
+              <Transition>PS</Transition>
+              <Ccs>1</Ccs>
+              <Index>#x1c12</Index>
+              <SubIndex>0</SubIndex>
+              <Data>00</Data>
+            </InitCmd>
+            <InitCmd>
+              <Transition>IP</Transition>
+              <Transition>PS</Transition>
+              <Ccs>1</Ccs>
+              <Index>#x6060</Index>
+              <SubIndex>0</SubIndex>
+              <Data>08</Data>
+            </InitCmd>
+            <InitCmd>
+              <Transition>PS</Transition>
+              <Ccs>1</Ccs>
+              <Index>#x60c2</Index>
+              <SubIndex>1</SubIndex>
+              <Data>e803</Data>
+            </InitCmd>
+            <InitCmd>
+              <Transition>SO</Transition>
+              <Ccs>1</Ccs>
+              <Index>#x2000</Index>
+              <SubIndex>0</SubIndex>
+              <Data>01</Data>
+            </InitCmd>
+          </InitCmds>
+        </CoE>
+      </Mailbox>
+      <DC>
+        <CycleTime0>1000000</CycleTime0>
+        <CycleTime1>0</CycleTime1>
+        <ShiftTime>-250000</ShiftTime>
+      </DC>
+    </Slave>
+  </Config>
+</EtherCATConfig>"#;
+
+    #[test]
+    fn parse_eni() {
+        let eni = Eni::parse(ENI).unwrap();
+        assert_eq!(eni.slaves.len(), 2);
+
+        let coupler = &eni.slaves[0];
+        assert_eq!(coupler.name, "Term 1 (EK1100)");
+        assert_eq!(
+            coupler.expected,
+            ExpectedSlave {
+                vendor_id: 2,
+                product_code: 0x044c_2c52,
+                revision: Some(0x0011_0000..=0x0011_0000),
+                alias: None,
+            }
+        );
+        assert_eq!(coupler.pdo_config, None);
+        assert!(coupler.startup_sdos.is_empty());
+        assert_eq!(coupler.dc_sync, None);
+
+        let drive = &eni.slaves[1];
+        assert_eq!(
+            drive.pdo_config,
+            Some(SlavePdoConfig {
+                sms: vec![
+                    SmPdoConfig {
+                        sm: ec::SmIdx::new(2),
+                        fixed: false,
+                        pdos: vec![PdoConfig {
+                            idx: ec::Idx::new(0x1600),
+                            entries: Some(vec![
+                                PdoEntryConfig {
+                                    sdo: ec::SdoIdx::new(0x6040, 0),
+                                    bit_len: 16,
+                                },
+                                PdoEntryConfig {
+                                    sdo: ec::SdoIdx::new(0, 0),
+                                    bit_len: 16,
+                                },
+                            ]),
+                        }],
+                    },
+                    SmPdoConfig {
+                        sm: ec::SmIdx::new(3),
+                        fixed: true,
+                        pdos: vec![PdoConfig {
+                            idx: ec::Idx::new(0x1A00),
+                            entries: None,
+                        }],
+                    },
+                ],
+            })
+        );
+        assert_eq!(
+            drive.startup_sdos,
+            vec![
+                StartupSdo {
+                    idx: ec::SdoIdx::new(0x6060, 0),
+                    value: ec::Value::Raw(vec![0x08]),
+                    verify: false,
+                },
+                StartupSdo {
+                    idx: ec::SdoIdx::new(0x60C2, 1),
+                    value: ec::Value::Raw(vec![0xE8, 0x03]),
+                    verify: false,
+                },
+            ]
+        );
+        assert_eq!(
+            drive.dc_sync,
+            Some(DcSync {
+                cycle_time: Duration::from_millis(1),
+                sync1_offset: None,
+                shift: -250_000,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_eni() {
+        assert!(matches!(
+            Eni::parse("<EtherCATConfig/>"),
+            Err(XmlError::MissingElement("Config"))
+        ));
+        let eni = ENI.replace("<VendorId>2</VendorId>", "<VendorId>#xZ</VendorId>");
+        assert!(matches!(
+            Eni::parse(&eni),
+            Err(XmlError::InvalidValue {
+                element: "VendorId",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_hex_data() {
+        assert_eq!(hex_bytes("e803"), Some(vec![0xE8, 0x03]));
+        assert_eq!(hex_bytes("e80"), None);
+    }
+}
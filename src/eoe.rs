@@ -0,0 +1,185 @@
+//! Ethernet over EtherCAT (EoE)
+//!
+//! Ethernet frames are tunneled through the mailbox of a slave,
+//! e.g. to reach the web server of a drive.
+//! On Linux an [`EoeBridge`] forwards them to a [`TapDevice`].
+
+use crate::{ctx, ec, Error, Master, Result};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+mod tap;
+
+#[cfg(target_os = "linux")]
+pub use self::tap::TapDevice;
+
+const EOE_TIMEOUT: Duration = Duration::from_micros(700_000);
+
+/// Waiting for a frame of a slave while polling a bridge
+#[cfg(target_os = "linux")]
+const EOE_POLL_TIMEOUT: Duration = Duration::from_micros(2_000);
+
+/// Buffer size of an Ethernet frame
+const EOE_MAX_FRAME_SIZE: usize = 1536;
+
+/// Received frames that have not been fetched yet.
+/// If the queue is full, the oldest frame is dropped.
+const EOE_MAX_QUEUED_FRAMES: usize = 64;
+
+/// Frames that the slaves sent via EoE
+pub(crate) type EoeFrames = Arc<Mutex<VecDeque<(ec::SlavePos, Vec<u8>)>>>;
+
+/// Collect the frames that SOEM receives with any mailbox transfer.
+pub(crate) fn register_eoe_hook(ctx: &mut ctx::Ctx) -> EoeFrames {
+    let frames = EoeFrames::default();
+    let queue = Arc::clone(&frames);
+    ctx.set_eoe_hook(move |slave, frame| {
+        let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+        push_frame(
+            &mut queue,
+            ec::SlavePos::new(slave.saturating_sub(1)),
+            frame.to_vec(),
+        );
+    });
+    frames
+}
+
+fn push_frame(queue: &mut VecDeque<(ec::SlavePos, Vec<u8>)>, slave: ec::SlavePos, frame: Vec<u8>) {
+    if queue.len() >= EOE_MAX_QUEUED_FRAMES {
+        log::warn!("EoE frame queue is full: drop the oldest frame");
+        queue.pop_front();
+    }
+    queue.push_back((slave, frame));
+}
+
+impl Master {
+    /// Set the IP parameters of an EoE port of a slave.
+    pub fn eoe_set_ip(
+        &mut self,
+        slave: ec::SlavePos,
+        port: u8,
+        param: &ctx::EoeIpParam,
+    ) -> Result<()> {
+        if self
+            .ctx
+            .eoe_set_ip(u16::from(slave) + 1, port, param, EOE_TIMEOUT)
+            <= 0
+        {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::EoeSetIp(slave));
+        }
+        Ok(())
+    }
+
+    /// Send an Ethernet frame to an EoE port of a slave.
+    pub fn eoe_send(&mut self, slave: ec::SlavePos, port: u8, frame: &[u8]) -> Result<()> {
+        if self
+            .ctx
+            .eoe_send(u16::from(slave) + 1, port, frame, EOE_TIMEOUT)
+            <= 0
+        {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::EoeSend(slave));
+        }
+        Ok(())
+    }
+
+    /// Receive the next Ethernet frame of a slave.
+    ///
+    /// Frames that were received with other mailbox transfers are
+    /// returned first, otherwise the mailbox is read once.
+    /// Returns `None` if there is no frame within the timeout.
+    pub fn eoe_recv(
+        &mut self,
+        slave: ec::SlavePos,
+        port: u8,
+        timeout: Duration,
+    ) -> Result<Option<Vec<u8>>> {
+        if let Some(frame) = self.pop_eoe_frame(slave) {
+            return Ok(Some(frame));
+        }
+        let mut buf = [0; EOE_MAX_FRAME_SIZE];
+        let (wkc, frame) = self
+            .ctx
+            .eoe_recv(u16::from(slave) + 1, port, &mut buf, timeout);
+        if wkc > 0 && !frame.is_empty() {
+            return Ok(Some(frame.to_vec()));
+        }
+        Ok(self.pop_eoe_frame(slave))
+    }
+
+    fn pop_eoe_frame(&mut self, slave: ec::SlavePos) -> Option<Vec<u8>> {
+        let mut queue = self
+            .eoe_frames
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let i = queue.iter().position(|(s, _)| *s == slave)?;
+        queue.remove(i).map(|(_, frame)| frame)
+    }
+}
+
+/// Bridge between an EoE port of a slave and a TAP device
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct EoeBridge {
+    slave: ec::SlavePos,
+    port: u8,
+    tap: TapDevice,
+    buf: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+impl EoeBridge {
+    #[must_use]
+    pub fn new(slave: ec::SlavePos, port: u8, tap: TapDevice) -> Self {
+        Self {
+            slave,
+            port,
+            tap,
+            buf: vec![0; EOE_MAX_FRAME_SIZE],
+        }
+    }
+
+    #[must_use]
+    pub const fn tap(&self) -> &TapDevice {
+        &self.tap
+    }
+
+    /// Forward the pending frames in both directions.
+    ///
+    /// It has to be called periodically, e.g. by the thread
+    /// that handles the mailbox communication.
+    /// Each received frame waits up to a mailbox timeout.
+    /// After [`Master::split`] the process data is exchanged
+    /// without the lock of the master (see
+    /// [`MailboxHandle::poll_eoe_bridge`](crate::MailboxHandle::poll_eoe_bridge)),
+    /// so polling doesn't delay the cycles.
+    pub fn poll(&mut self, master: &mut Master) -> Result<()> {
+        while let Some(len) = self.tap.read_frame(&mut self.buf)? {
+            master.eoe_send(self.slave, self.port, &self.buf[..len])?;
+        }
+        while let Some(frame) = master.eoe_recv(self.slave, self.port, EOE_POLL_TIMEOUT)? {
+            self.tap.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_oldest_frame_if_queue_is_full() {
+        let mut queue = VecDeque::new();
+        for i in 0..=EOE_MAX_QUEUED_FRAMES {
+            push_frame(&mut queue, ec::SlavePos::new(0), vec![i as u8]);
+        }
+        assert_eq!(queue.len(), EOE_MAX_QUEUED_FRAMES);
+        assert_eq!(queue[0].1, vec![1]);
+    }
+}
@@ -1,5 +1,7 @@
-use crate::AlStatus;
+use crate::{AlStatus, SdoAbortCode, StartupSdoError, TopologyMismatch};
+use ethercat_soem_ctx as ctx;
 use ethercat_types as ec;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Error
@@ -11,38 +13,114 @@ pub enum Error {
     Iface,
     #[error("No slaves found")]
     NoSlaves,
+    #[error("Too many slaves found")]
+    TooManySlaves,
+    #[error("{} slave(s) do not match the expected topology", .0.len())]
+    Topology(Vec<TopologyMismatch>),
     #[error("Could not configure map group")]
     CfgMapGroup,
+    #[error("Process image ({required} bytes) exceeds the I/O map capacity ({capacity} bytes)")]
+    IoMapSize { required: usize, capacity: usize },
     #[error("Could not configure DC")]
     CfgDc,
+    #[error("{0:?} does not support DC")]
+    NoDc(ec::SlavePos),
+    #[error("DC time {0:?} is out of range")]
+    DcTime(Duration),
     #[error("Could not set requested state")]
     SetState,
+    /// Contains the AL status codes of the slaves that report an error.
     #[error("Could not check state")]
-    CheckState,
+    CheckState(Vec<(ec::SlavePos, AlStatus)>),
+    #[error("Master is not in redundant mode")]
+    NotRedundant,
     #[error("Could not read states")]
     ReadStates,
     #[error("Could not send process data")]
     SendProcessData,
     #[error("Could not receive process data")]
     RecvProcessData,
-    #[error("Invalid AL state: {0:?}")]
+    #[error("Invalid AL state: {0}")]
     AlState(AlStatus),
     #[error("Invalid group ID")]
     GroupId,
-    #[error("Could not read OD list of {0:?}")]
-    ReadOdList(ec::SlavePos),
-    #[error("Could not read OD description of {0:?}")]
-    ReadOdDesc(ec::SdoPos),
-    #[error("Could not read OE list of {0:?}")]
-    ReadOeList(ec::SdoPos),
-    #[error("Could not read {1:?} of {0:?}")]
-    ReadSdo(ec::SlavePos, ec::SdoIdx),
+    /// Groups `> 0` are used, but the slave is still in group `0`.
+    #[error("{0:?} is not assigned to a group")]
+    UngroupedSlave(ec::SlavePos),
+    #[error("Could not read OD list of {slave:?}{}", abort_reason(.abort_code))]
+    ReadOdList {
+        slave: ec::SlavePos,
+        abort_code: Option<SdoAbortCode>,
+        /// Errors reported by SOEM
+        errors: Vec<ctx::Error>,
+    },
+    #[error("Could not read OD description of {pos:?}{}", abort_reason(.abort_code))]
+    ReadOdDesc {
+        pos: ec::SdoPos,
+        abort_code: Option<SdoAbortCode>,
+        /// Errors reported by SOEM
+        errors: Vec<ctx::Error>,
+    },
+    #[error("Could not read OE list of {pos:?}{}", abort_reason(.abort_code))]
+    ReadOeList {
+        pos: ec::SdoPos,
+        abort_code: Option<SdoAbortCode>,
+        /// Errors reported by SOEM
+        errors: Vec<ctx::Error>,
+    },
+    #[error("Could not read {idx:?} of {slave:?}{}", abort_reason(.abort_code))]
+    ReadSdo {
+        slave: ec::SlavePos,
+        idx: ec::SdoIdx,
+        abort_code: Option<SdoAbortCode>,
+        /// Errors reported by SOEM
+        errors: Vec<ctx::Error>,
+    },
     #[error("Index {1:?} not found at {0:?}")]
     IdxNotFound(ec::SlavePos, ec::Idx),
     #[error("Subindex {1:?} not found at {0:?}")]
     SubIdxNotFound(ec::SlavePos, ec::SdoIdx),
-    #[error("Could not write {1:?} of {0:?}")]
-    WriteSdo(ec::SlavePos, ec::SdoIdx),
+    #[error("Could not write {idx:?} of {slave:?}{}", abort_reason(.abort_code))]
+    WriteSdo {
+        slave: ec::SlavePos,
+        idx: ec::SdoIdx,
+        abort_code: Option<SdoAbortCode>,
+        /// Errors reported by SOEM
+        errors: Vec<ctx::Error>,
+    },
+    #[error("Could not read IDN {2} of drive {1} of {0:?}")]
+    ReadIdn(ec::SlavePos, u8, u16),
+    #[error("Could not write IDN {2} of drive {1} of {0:?}")]
+    WriteIdn(ec::SlavePos, u8, u16),
+    #[error("Could not read SII of {0:?}")]
+    ReadSii(ec::SlavePos),
+    #[error("Could not write SII word 0x{1:X} of {0:?}")]
+    WriteSii(ec::SlavePos, u16),
+    #[error("SII word 0x{1:X} of {0:?} differs from the written data")]
+    VerifySii(ec::SlavePos, u16),
+    #[error("Invalid SII length ({0} bytes)")]
+    SiiLength(usize),
+    #[error("SII data ({0} bytes) is truncated")]
+    SiiTruncated(usize),
+    #[error("Could not read file {1:?} from {0:?} via FoE")]
+    FoeRead(ec::SlavePos, String),
+    #[error("Could not write file {1:?} to {0:?} via FoE")]
+    FoeWrite(ec::SlavePos, String),
+    #[error("Invalid file name {0:?}")]
+    FileName(String),
+    #[error("Could not configure the bootstrap mailbox of {0:?}")]
+    BootMailbox(ec::SlavePos),
+    #[error("Could not configure the standard mailbox of {0:?}")]
+    StandardMailbox(ec::SlavePos),
+    #[error("Could not set the EoE IP parameters of {0:?}")]
+    EoeSetIp(ec::SlavePos),
+    #[error("Could not send an EoE frame to {0:?}")]
+    EoeSend(ec::SlavePos),
+    /// Contains a [`Error::WriteSdo`] for each slave that could not be configured.
+    #[error("Could not write the PDO configuration of {} slave(s)", .0.len())]
+    PdoConfig(Vec<Error>),
+    #[error("Could not apply {} startup SDO(s)", .0.len())]
+    StartupSdos(Vec<StartupSdoError>),
     #[error("Data type ({0:?}) is not supported yet")]
     UnsuportedDataType(ec::DataType),
     #[error("Value ({0:?}) is not supported yet")]
@@ -53,6 +131,8 @@ pub enum Error {
     ValueConversion(#[from] std::array::TryFromSliceError),
     #[error("Unexpected data type")]
     UnexpectedDataType, // TODO: add expected and actual
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
     #[error("No frame received")]
     NoFrame,
     #[error("Unkown frame received")]
@@ -63,6 +143,79 @@ pub enum Error {
     SlaveNotFound(ec::SlavePos),
     #[error("PDO entry {0:?} not found")]
     PdoEntryNotFound(ec::PdoEntryIdx),
+    #[error("PDO entry {0:?} is out of the process data")]
+    PdoEntryOutOfRange(ec::PdoEntryIdx),
+}
+
+impl Error {
+    pub(crate) fn read_sdo(slave: ec::SlavePos, idx: ec::SdoIdx, errors: Vec<ctx::Error>) -> Self {
+        Self::ReadSdo {
+            slave,
+            idx,
+            abort_code: sdo_abort_code(&errors),
+            errors,
+        }
+    }
+
+    pub(crate) fn write_sdo(slave: ec::SlavePos, idx: ec::SdoIdx, errors: Vec<ctx::Error>) -> Self {
+        Self::WriteSdo {
+            slave,
+            idx,
+            abort_code: sdo_abort_code(&errors),
+            errors,
+        }
+    }
+
+    pub(crate) fn read_od_list(slave: ec::SlavePos, errors: Vec<ctx::Error>) -> Self {
+        Self::ReadOdList {
+            slave,
+            abort_code: sdo_abort_code(&errors),
+            errors,
+        }
+    }
+
+    pub(crate) fn read_od_desc(pos: ec::SdoPos, errors: Vec<ctx::Error>) -> Self {
+        Self::ReadOdDesc {
+            pos,
+            abort_code: sdo_abort_code(&errors),
+            errors,
+        }
+    }
+
+    pub(crate) fn read_oe_list(pos: ec::SdoPos, errors: Vec<ctx::Error>) -> Self {
+        Self::ReadOeList {
+            pos,
+            abort_code: sdo_abort_code(&errors),
+            errors,
+        }
+    }
+
+    /// The abort code of a failed SDO or SDO information transfer
+    #[must_use]
+    pub const fn sdo_abort_code(&self) -> Option<SdoAbortCode> {
+        match self {
+            Self::ReadSdo { abort_code, .. }
+            | Self::WriteSdo { abort_code, .. }
+            | Self::ReadOdList { abort_code, .. }
+            | Self::ReadOdDesc { abort_code, .. }
+            | Self::ReadOeList { abort_code, .. } => *abort_code,
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn sdo_abort_code(errors: &[ctx::Error]) -> Option<SdoAbortCode> {
+    errors
+        .iter()
+        .find(|e| matches!(e.err_type, ctx::ErrType::Sdo | ctx::ErrType::SdoInfo))
+        .map(|e| SdoAbortCode::from(e.abort_code as u32))
+}
+
+fn abort_reason(abort_code: &Option<SdoAbortCode>) -> String {
+    match abort_code {
+        Some(code) => format!(": {}", code),
+        None => String::new(),
+    }
 }
 
 impl From<ec::InvalidSmTypeError> for Error {
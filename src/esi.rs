@@ -0,0 +1,98 @@
+//! Device descriptions of EtherCAT Slave Information (ESI) files
+//!
+//! Slaves without CoE SDO information can't provide
+//! their object dictionary, so the master falls back to
+//! the description of a matching ESI device (see [`Master::add_esi`]).
+
+use crate::{ec, Master, PdoDescription, SlaveIdentity};
+
+#[cfg(feature = "esi")]
+mod parse;
+
+/// Device descriptions of ESI files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Esi {
+    pub devices: Vec<EsiDevice>,
+}
+
+/// Description of a slave type
+#[derive(Debug, Clone, PartialEq)]
+pub struct EsiDevice {
+    pub identity: SlaveIdentity,
+    pub name: String,
+    /// Object dictionary
+    pub objects: Vec<(ec::SdoInfo, Vec<Option<ec::SdoEntryInfo>>)>,
+    pub pdos: Vec<PdoDescription>,
+}
+
+impl Esi {
+    /// Find the description of a slave type.
+    ///
+    /// A device with the same revision is preferred,
+    /// otherwise the latest revision is used.
+    #[must_use]
+    pub fn device(&self, vendor_id: u32, product_code: u32, revision: u32) -> Option<&EsiDevice> {
+        let devices = self.devices.iter().filter(|d| {
+            d.identity.vendor_id == vendor_id && d.identity.product_code == product_code
+        });
+        let exact = devices
+            .clone()
+            .find(|d| d.identity.matches(vendor_id, product_code, revision));
+        exact.or_else(|| devices.max_by_key(|d| d.identity.revision))
+    }
+}
+
+impl Master {
+    /// Add the device descriptions of an ESI file.
+    ///
+    /// They are used if a slave can't provide its object dictionary
+    /// or PDO mapping via CoE.
+    pub fn add_esi(&mut self, esi: Esi) {
+        self.esi.devices.extend(esi.devices);
+    }
+
+    pub(crate) fn esi_device(&self, slave: ec::SlavePos) -> Option<&EsiDevice> {
+        let s = self.slaves().get(usize::from(slave))?;
+        self.esi.device(s.eep_man(), s.eep_id(), s.eep_rev())
+    }
+
+    pub(crate) fn esi_pdo(&self, slave: ec::SlavePos, idx: ec::Idx) -> Option<&PdoDescription> {
+        self.esi_device(slave)?
+            .pdos
+            .iter()
+            .find(|pdo| pdo.idx == idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(revision: u32) -> EsiDevice {
+        EsiDevice {
+            identity: SlaveIdentity {
+                vendor_id: 2,
+                product_code: 0x03f0_3052,
+                revision: Some(revision),
+            },
+            name: String::new(),
+            objects: vec![],
+            pdos: vec![],
+        }
+    }
+
+    #[test]
+    fn find_device_by_identity() {
+        let esi = Esi {
+            devices: vec![
+                device(0x0010_0000),
+                device(0x0013_0000),
+                device(0x0011_0000),
+            ],
+        };
+        let found = |rev| esi.device(2, 0x03f0_3052, rev).unwrap().identity.revision;
+        assert_eq!(found(0x0011_0000), Some(0x0011_0000));
+        assert_eq!(found(0x0012_0000), Some(0x0013_0000));
+        assert!(esi.device(2, 0x03f0_3053, 0x0011_0000).is_none());
+    }
+}
@@ -0,0 +1,427 @@
+use super::{Esi, EsiDevice};
+use crate::{
+    access, ec,
+    xml::{
+        attr_u32, child, children, flag, text, u16_value, u32_value, u8_value, Result, XmlError,
+    },
+    PdoDescription, PdoEntryDescription, SlaveIdentity,
+};
+use num_traits::cast::FromPrimitive;
+use roxmltree::Node;
+use std::{collections::HashMap, fs, path::Path};
+
+const OBJECT_CODE_VAR: u8 = 0x07;
+const OBJECT_CODE_ARRAY: u8 = 0x08;
+const OBJECT_CODE_RECORD: u8 = 0x09;
+
+type DataTypes<'a, 'i> = HashMap<&'a str, Node<'a, 'i>>;
+type Object = (ec::SdoInfo, Vec<Option<ec::SdoEntryInfo>>);
+
+impl Esi {
+    /// Read an ESI file.
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let xml = fs::read_to_string(path)?;
+        Self::parse(&xml)
+    }
+
+    /// Parse the XML of an ESI file.
+    ///
+    /// Devices, objects and PDOs that can't be parsed
+    /// are skipped with a warning.
+    pub fn parse(xml: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(xml)?;
+        let root = doc.root_element();
+        let vendor = child(root, "Vendor").ok_or(XmlError::MissingElement("Vendor"))?;
+        let vendor_id = u32_value(vendor, "Id")?;
+        let devices = child(root, "Descriptions")
+            .and_then(|d| child(d, "Devices"))
+            .ok_or(XmlError::MissingElement("Devices"))?;
+        let devices = parse_valid("device", children(devices, "Device"), |d| {
+            parse_device(vendor_id, d)
+        });
+        Ok(Self { devices })
+    }
+}
+
+/// Parse the nodes and skip the invalid ones.
+fn parse_valid<'a, 'i: 'a, T, F>(
+    kind: &str,
+    nodes: impl Iterator<Item = Node<'a, 'i>>,
+    mut parse: F,
+) -> Vec<T>
+where
+    F: FnMut(Node<'a, 'i>) -> Result<T>,
+{
+    nodes
+        .filter_map(|node| match parse(node) {
+            Ok(v) => Some(v),
+            Err(err) => {
+                let idx = text(node, "Index").or_else(|_| text(node, "Type"));
+                log::warn!(
+                    "Skip invalid ESI {} {} (line {}): {}",
+                    kind,
+                    idx.unwrap_or_default(),
+                    node.document().text_pos_at(node.range().start).row,
+                    err
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_device(vendor_id: u32, device: Node<'_, '_>) -> Result<EsiDevice> {
+    let ty = child(device, "Type").ok_or(XmlError::MissingElement("Type"))?;
+    let product_code =
+        attr_u32(ty, "ProductCode")?.ok_or(XmlError::MissingAttribute("ProductCode"))?;
+    let identity = SlaveIdentity {
+        vendor_id,
+        product_code,
+        revision: attr_u32(ty, "RevisionNo")?,
+    };
+    let name = child(device, "Name")
+        .or(Some(ty))
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let objects = match child(device, "Profile").and_then(|p| child(p, "Dictionary")) {
+        Some(dictionary) => parse_dictionary(dictionary),
+        None => vec![],
+    };
+    let pdos = device
+        .children()
+        .filter(|n| n.has_tag_name("RxPdo") || n.has_tag_name("TxPdo"));
+    let pdos = parse_valid("PDO", pdos, parse_pdo);
+    Ok(EsiDevice {
+        identity,
+        name,
+        objects,
+        pdos,
+    })
+}
+
+fn parse_dictionary(dictionary: Node<'_, '_>) -> Vec<Object> {
+    let data_types: DataTypes<'_, '_> = child(dictionary, "DataTypes")
+        .map(|types| {
+            children(types, "DataType")
+                .filter_map(|t| Some((child(t, "Name")?.text()?.trim(), t)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let objects = match child(dictionary, "Objects") {
+        Some(objects) => objects,
+        None => return vec![],
+    };
+    let mut res = parse_valid("object", children(objects, "Object"), |obj| {
+        parse_object(obj, &data_types)
+    });
+    for (pos, (info, _)) in res.iter_mut().enumerate() {
+        info.pos = ec::SdoPos::new(pos as u16);
+    }
+    res
+}
+
+/// The position is set by the caller.
+fn parse_object(obj: Node<'_, '_>, data_types: &DataTypes<'_, '_>) -> Result<Object> {
+    let name = text(obj, "Name")?.to_string();
+    let type_name = text(obj, "Type")?;
+    let access = parse_access(obj).unwrap_or(READ_ONLY);
+    let complex_type = data_types
+        .get(type_name)
+        .filter(|t| child(**t, "SubItem").is_some());
+    let (object_code, entries) = match complex_type {
+        Some(t) => parse_sub_items(*t, data_types, access)?,
+        None => {
+            let bit_len = u16_value(obj, "BitSize")?;
+            let entry = entry_info(type_name, bit_len, access, name.clone());
+            (OBJECT_CODE_VAR, vec![Some(entry)])
+        }
+    };
+    let info = ec::SdoInfo {
+        pos: ec::SdoPos::new(0),
+        idx: ec::Idx::new(u16_value(obj, "Index")?),
+        max_sub_idx: ec::SubIdx::new(entries.len().saturating_sub(1) as u8),
+        object_code: Some(object_code),
+        name,
+    };
+    Ok((info, entries))
+}
+
+/// Entries of records and arrays
+fn parse_sub_items(
+    data_type: Node<'_, '_>,
+    data_types: &DataTypes<'_, '_>,
+    access: ec::SdoEntryAccess,
+) -> Result<(u8, Vec<Option<ec::SdoEntryInfo>>)> {
+    let mut object_code = OBJECT_CODE_RECORD;
+    let mut entries = vec![];
+    for item in children(data_type, "SubItem") {
+        let name = text(item, "Name")?;
+        let type_name = text(item, "Type")?;
+        let bit_len = u16_value(item, "BitSize")?;
+        let access = parse_access(item).unwrap_or(access);
+        if child(item, "SubIdx").is_some() {
+            let entry = entry_info(type_name, bit_len, access, name.to_string());
+            insert(&mut entries, u8_value(item, "SubIdx")?, entry);
+            continue;
+        }
+        // Array elements don't have a subindex.
+        let (array, info) = data_types
+            .get(type_name)
+            .and_then(|a| Some((*a, child(*a, "ArrayInfo")?)))
+            .ok_or(XmlError::MissingElement("ArrayInfo"))?;
+        let base_type = text(array, "BaseType")?;
+        let lower_bound = u8_value(info, "LBound")?;
+        let elements = u8_value(info, "Elements")?;
+        let element_bit_len = bit_len / u16::from(elements.max(1));
+        for i in 0..elements {
+            let sub_idx = lower_bound.saturating_add(i);
+            let name = format!("SubIndex {:03}", sub_idx);
+            let entry = entry_info(base_type, element_bit_len, access, name);
+            insert(&mut entries, sub_idx, entry);
+        }
+        object_code = OBJECT_CODE_ARRAY;
+    }
+    Ok((object_code, entries))
+}
+
+fn insert(entries: &mut Vec<Option<ec::SdoEntryInfo>>, sub_idx: u8, entry: ec::SdoEntryInfo) {
+    let i = sub_idx as usize;
+    if entries.len() <= i {
+        entries.resize(i + 1, None);
+    }
+    entries[i] = Some(entry);
+}
+
+fn entry_info(
+    type_name: &str,
+    bit_len: u16,
+    access: ec::SdoEntryAccess,
+    description: String,
+) -> ec::SdoEntryInfo {
+    ec::SdoEntryInfo {
+        data_type: data_type(type_name),
+        bit_len,
+        access,
+        description,
+    }
+}
+
+const READ_ONLY: ec::SdoEntryAccess = ec::SdoEntryAccess {
+    pre_op: ec::Access::ReadOnly,
+    safe_op: ec::Access::ReadOnly,
+    op: ec::Access::ReadOnly,
+};
+
+/// Parse the access rights (`ro`, `rw` or `wo`)
+/// that might be restricted to some states (e.g. `PreOP_SafeOP`).
+fn parse_access(node: Node<'_, '_>) -> Option<ec::SdoEntryAccess> {
+    let access_node = child(node, "Flags").and_then(|f| child(f, "Access"))?;
+    let rights = access_node.text()?.trim();
+    let allowed = |restrictions: Option<&str>, state: &str| match restrictions {
+        Some(r) => r.split('_').any(|s| s == state),
+        None => true,
+    };
+    let state = |state| {
+        let read =
+            rights.contains('r') && allowed(access_node.attribute("ReadRestrictions"), state);
+        let write =
+            rights.contains('w') && allowed(access_node.attribute("WriteRestrictions"), state);
+        access(read, write)
+    };
+    Some(ec::SdoEntryAccess {
+        pre_op: state("PreOP"),
+        safe_op: state("SafeOP"),
+        op: state("OP"),
+    })
+}
+
+/// Map the name of an ESI base type to the CoE data type.
+fn data_type(name: &str) -> ec::DataType {
+    let code = match name {
+        "BOOL" | "BIT" | "BIT1" => 0x0001,
+        "SINT" => 0x0002,
+        "INT" => 0x0003,
+        "DINT" => 0x0004,
+        "USINT" => 0x0005,
+        "UINT" => 0x0006,
+        "UDINT" => 0x0007,
+        "REAL" => 0x0008,
+        "INT24" => 0x0010,
+        "LREAL" => 0x0011,
+        "LINT" => 0x0015,
+        "ULINT" => 0x001B,
+        "BYTE" => 0x001E,
+        "WORD" => 0x001F,
+        "DWORD" => 0x0020,
+        _ if name.starts_with("STRING(") => 0x0009,
+        _ => match name.strip_prefix("BIT").and_then(|n| n.parse::<u16>().ok()) {
+            Some(n @ 2..=8) => 0x0030 + n - 1,
+            _ => return ec::DataType::Raw,
+        },
+    };
+    ec::DataType::from_u16(code).unwrap_or(ec::DataType::Raw)
+}
+
+fn parse_pdo(pdo: Node<'_, '_>) -> Result<PdoDescription> {
+    let sm_type = if pdo.has_tag_name("RxPdo") {
+        ec::SmType::Outputs
+    } else {
+        ec::SmType::Inputs
+    };
+    let sm = attr_u32(pdo, "Sm")?.map(|sm| ec::SmIdx::new(sm as u8));
+    let entries = children(pdo, "Entry")
+        .map(parse_pdo_entry)
+        .collect::<Result<_>>()?;
+    Ok(PdoDescription {
+        idx: ec::Idx::new(u16_value(pdo, "Index")?),
+        name: text(pdo, "Name").unwrap_or_default().to_string(),
+        sm_type,
+        sm,
+        fixed: flag(pdo.attribute("Fixed")),
+        entries,
+    })
+}
+
+fn parse_pdo_entry(entry: Node<'_, '_>) -> Result<PdoEntryDescription> {
+    let idx = u16_value(entry, "Index")?;
+    // Gaps have no subindex, name and data type.
+    let sub_idx = match child(entry, "SubIndex") {
+        Some(_) => u8_value(entry, "SubIndex")?,
+        None => 0,
+    };
+    Ok(PdoEntryDescription {
+        sdo: ec::SdoIdx::new(idx, sub_idx),
+        bit_len: u8_value(entry, "BitLen")?,
+        name: text(entry, "Name").unwrap_or_default().to_string(),
+        data_type: text(entry, "DataType").map_or(ec::DataType::Raw, data_type),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ESI: &str = r##"<?xml version="1.0"?>
+<EtherCATInfo>
+  <Vendor><Id>#x00000002</Id><Name>Beckhoff Automation GmbH</Name></Vendor>
+  <Descriptions>
+    <Devices>
+      <Device Physics="YY">
+        <Type ProductCode="#x03f03052" RevisionNo="#x00120000">EL1008</Type>
+        <Name LcId="1033">EL1008 8Ch. Dig. Input 24V, 3ms</Name>
+        <TxPdo Fixed="1" Sm="0">
+          <Index>#x1a00</Index>
+          <Name>Channel 1</Name>
+          <Entry>
+            <Index>#x6000</Index>
+            <SubIndex>1</SubIndex>
+            <BitLen>1</BitLen>
+            <Name>Input</Name>
+            <DataType>BOOL</DataType>
+          </Entry>
+          <Entry><Index>#x0</Index><BitLen>7</BitLen></Entry>
+        </TxPdo>
+        <TxPdo Sm="0"><Name>Missing index</Name></TxPdo>
+      </Device>
+      <Device Physics="YY">
+        <Type ProductCode="#x0bb83052" RevisionNo="#x00100000">EL3000</Type>
+        <Name>EL3000</Name>
+        <Profile>
+          <Dictionary>
+            <DataTypes>
+              <DataType><Name>DT1C12ARR</Name><BaseType>UINT</BaseType><BitSize>32</BitSize>
+                <ArrayInfo><LBound>1</LBound><Elements>2</Elements></ArrayInfo>
+              </DataType>
+              <DataType><Name>DT1C12</Name><BitSize>48</BitSize>
+                <SubItem><SubIdx>0</SubIdx><Name>SubIndex 000</Name><Type>USINT</Type><BitSize>8</BitSize></SubItem>
+                <SubItem><Name>Elements</Name><Type>DT1C12ARR</Type><BitSize>32</BitSize>
+                  <Flags><Access WriteRestrictions="PreOP">rw</Access></Flags>
+                </SubItem>
+              </DataType>
+            </DataTypes>
+            <Objects>
+              <Object>
+                <Index>#x1000</Index><Name>Device type</Name><Type>UDINT</Type><BitSize>32</BitSize>
+                <Flags><Access>ro</Access></Flags>
+              </Object>
+              <Object>
+                <Index>#x1008</Index><Name>Missing bit size</Name><Type>STRING(6)</Type>
+              </Object>
+              <Object>
+                <Index>#x1c12</Index><Name>RxPDO assign</Name><Type>DT1C12</Type><BitSize>48</BitSize>
+                <Flags><Access>ro</Access></Flags>
+              </Object>
+            </Objects>
+          </Dictionary>
+        </Profile>
+      </Device>
+    </Devices>
+  </Descriptions>
+</EtherCATInfo>"##;
+
+    #[test]
+    fn parse_esi() {
+        let esi = Esi::parse(ESI).unwrap();
+        assert_eq!(esi.devices.len(), 2);
+
+        let dev = esi.device(2, 0x03f0_3052, 0x0012_0000).unwrap();
+        assert_eq!(dev.name, "EL1008 8Ch. Dig. Input 24V, 3ms");
+        assert!(dev.objects.is_empty());
+        assert_eq!(
+            dev.pdos,
+            vec![PdoDescription {
+                idx: ec::Idx::new(0x1A00),
+                name: "Channel 1".to_string(),
+                sm_type: ec::SmType::Inputs,
+                sm: Some(ec::SmIdx::new(0)),
+                fixed: true,
+                entries: vec![
+                    PdoEntryDescription {
+                        sdo: ec::SdoIdx::new(0x6000, 1),
+                        bit_len: 1,
+                        name: "Input".to_string(),
+                        data_type: ec::DataType::Bool,
+                    },
+                    PdoEntryDescription {
+                        sdo: ec::SdoIdx::new(0, 0),
+                        bit_len: 7,
+                        name: String::new(),
+                        data_type: ec::DataType::Raw,
+                    },
+                ],
+            }]
+        );
+
+        let dev = &esi.devices[1];
+        let (info, entries) = &dev.objects[0];
+        assert_eq!(info.idx, ec::Idx::new(0x1000));
+        assert_eq!(info.object_code, Some(OBJECT_CODE_VAR));
+        assert_eq!(entries.len(), 1);
+        let entry = entries[0].as_ref().unwrap();
+        assert_eq!(entry.data_type, ec::DataType::U32);
+        assert_eq!(entry.description, "Device type");
+
+        // The invalid object is skipped.
+        assert_eq!(dev.objects.len(), 2);
+        let (info, entries) = &dev.objects[1];
+        assert_eq!(info.pos, ec::SdoPos::new(1));
+        assert_eq!(info.max_sub_idx, ec::SubIdx::new(2));
+        assert_eq!(info.object_code, Some(OBJECT_CODE_ARRAY));
+        let entry = entries[2].as_ref().unwrap();
+        assert_eq!(entry.data_type, ec::DataType::U16);
+        assert_eq!(entry.bit_len, 16);
+        assert_eq!(entry.description, "SubIndex 002");
+        assert_eq!(entry.access.pre_op, ec::Access::ReadWrite);
+        assert_eq!(entry.access.op, ec::Access::ReadOnly);
+        assert_eq!(entries[0].as_ref().unwrap().access, READ_ONLY);
+    }
+
+    #[test]
+    fn map_data_types() {
+        assert_eq!(data_type("UINT"), ec::DataType::U16);
+        assert_eq!(data_type("STRING(8)"), ec::DataType::String);
+        assert_eq!(data_type("UNKNOWN"), ec::DataType::Raw);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::AlStatus;
+use ethercat_types as ec;
+
+/// Event reported by the master
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The working counter of a group is below the expected value.
+    WkcMismatch {
+        group: u8,
+        expected: usize,
+        actual: usize,
+    },
+    /// The working counter of a group reached the expected value again.
+    WkcRestored { group: u8 },
+    /// A slave left the operational state.
+    ///
+    /// It's reported again if the state changes
+    /// or after the slave was operational again.
+    SlaveNotOp {
+        slave: ec::SlavePos,
+        state: ec::AlState,
+        /// The slave signals an error that has to be acknowledged.
+        error: bool,
+        al_status: AlStatus,
+    },
+    /// A slave does not respond anymore.
+    SlaveLost { slave: ec::SlavePos },
+    /// The error of a slave in SAFE-OP was acknowledged.
+    SlaveErrorAck { slave: ec::SlavePos },
+    /// A slave in SAFE-OP was requested to go to OP.
+    SlaveOpRequested { slave: ec::SlavePos },
+    /// A slave was reconfigured.
+    SlaveReconfigured { slave: ec::SlavePos },
+    /// A lost slave was recovered.
+    SlaveRecovered { slave: ec::SlavePos },
+    /// A lost slave responds again.
+    SlaveFound { slave: ec::SlavePos },
+    /// A slave could not be recovered or reconfigured.
+    RecoveryFailed { slave: ec::SlavePos },
+}
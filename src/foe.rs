@@ -0,0 +1,140 @@
+use crate::{ec, sii::EEPROM_TIMEOUT, AlStatus, Error, Master, Result, EC_NOFRAME};
+use std::{ffi::CString, time::Duration};
+
+const FOE_TIMEOUT: Duration = Duration::from_millis(10_000);
+
+/// Entering BOOT might take a while (e.g. to erase the flash).
+const BOOT_STATE_TIMEOUT: Duration = Duration::from_millis(20_000);
+
+impl Master {
+    /// Read a file from a slave via FoE.
+    ///
+    /// The file must fit into `target`.
+    /// It returns the read part of `target`.
+    pub fn foe_read<'t>(
+        &mut self,
+        slave: ec::SlavePos,
+        filename: &str,
+        password: u32,
+        target: &'t mut [u8],
+    ) -> Result<&'t mut [u8]> {
+        let pos = self.foe_slave(slave)?;
+        let name = foe_filename(filename)?;
+        let (wkc, file) = self.ctx.foe_read(pos, &name, password, target, FOE_TIMEOUT);
+        if wkc <= 0 {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::FoeRead(slave, filename.to_string()));
+        }
+        Ok(file)
+    }
+
+    /// Write a file to a slave via FoE.
+    pub fn foe_write(
+        &mut self,
+        slave: ec::SlavePos,
+        filename: &str,
+        password: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        let pos = self.foe_slave(slave)?;
+        let name = foe_filename(filename)?;
+        if self.ctx.foe_write(pos, &name, password, data, FOE_TIMEOUT) <= 0 {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::FoeWrite(slave, filename.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Register a callback that is called for each FoE packet
+    /// with the slave, the packet number and the data size.
+    pub fn set_foe_progress<F>(&mut self, mut f: F)
+    where
+        F: FnMut(ec::SlavePos, usize, usize) + Send + 'static,
+    {
+        self.ctx.set_foe_hook(move |slave, packet, size| {
+            f(
+                ec::SlavePos::new(slave.saturating_sub(1)),
+                packet.max(0) as usize,
+                size.max(0) as usize,
+            );
+        });
+    }
+
+    /// Update the firmware of a slave.
+    ///
+    /// The slave is switched to BOOT via INIT, the file is written
+    /// via FoE and the slave is reset to INIT afterwards.
+    ///
+    /// The bootstrap mailbox that is used in BOOT is replaced
+    /// by the standard mailbox again, so the slave can be used
+    /// without [`Master::auto_config`]. If the new firmware comes
+    /// with a different SII, the slaves have to be configured again.
+    pub fn update_firmware(
+        &mut self,
+        slave: ec::SlavePos,
+        filename: &str,
+        password: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        let pos = self.foe_slave(slave)?;
+        self.set_slave_state(pos, ec::AlState::Init, BOOT_STATE_TIMEOUT)?;
+        if self.ctx.config_boot_mailbox(pos, EEPROM_TIMEOUT) <= 0 {
+            return Err(Error::BootMailbox(slave));
+        }
+        self.set_slave_state(pos, ec::AlState::Boot, BOOT_STATE_TIMEOUT)?;
+        log::info!("Write firmware {:?} to {:?}", filename, slave);
+        let res = self.foe_write(slave, filename, password, data);
+        if let Err(err) = self.set_slave_state(pos, ec::AlState::Init, BOOT_STATE_TIMEOUT) {
+            log::warn!("Could not reset {:?} to INIT: {}", slave, err);
+        }
+        let restored = self.ctx.config_standard_mailbox(pos, EEPROM_TIMEOUT) > 0;
+        res?;
+        if !restored {
+            return Err(Error::StandardMailbox(slave));
+        }
+        Ok(())
+    }
+
+    /// The SOEM slave number
+    fn foe_slave(&self, slave: ec::SlavePos) -> Result<u16> {
+        if usize::from(slave) >= self.slaves().len() {
+            return Err(Error::SlaveNotFound(slave));
+        }
+        Ok(u16::from(slave) + 1)
+    }
+
+    fn set_slave_state(&mut self, pos: u16, state: ec::AlState, timeout: Duration) -> Result<()> {
+        let s = u8::from(state) as u16;
+        self.ctx.slaves_mut()[pos as usize].set_state(s);
+        match self.ctx.write_state(pos) {
+            EC_NOFRAME => return Err(Error::NoFrame),
+            0 => return Err(Error::SetState),
+            _ => {}
+        }
+        if self.ctx.state_check(pos, s, timeout) != s {
+            log::warn!("Slave {} did not reach {:?}", pos, state);
+            let code = self.ctx.slaves()[pos as usize].al_status_code();
+            let slave = ec::SlavePos::new(pos - 1);
+            return Err(Error::CheckState(vec![(slave, AlStatus::from(code))]));
+        }
+        Ok(())
+    }
+}
+
+fn foe_filename(filename: &str) -> Result<CString> {
+    CString::new(filename).map_err(|_| Error::FileName(filename.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_filename_with_nul() {
+        assert!(foe_filename("firmware.efw").is_ok());
+        assert!(matches!(
+            foe_filename("firm\0ware"),
+            Err(Error::FileName(_))
+        ));
+    }
+}
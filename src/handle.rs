@@ -0,0 +1,595 @@
+use crate::supervision::WkcCheck;
+#[cfg(target_os = "linux")]
+use crate::EoeBridge;
+use crate::{
+    ctx,
+    cycle::{Cycle, CycleRunner},
+    ec, pdo, Emergency, EoeIpParam, Error, Event, IdnElements, Master, PdoInfo, RecoveryConfig,
+    RedundancyStatus, Result, SdoInfo, DEFAULT_RECV_TIMEOUT,
+};
+use std::{
+    ops::ControlFlow,
+    sync::mpsc::{self, Receiver, Sender},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+struct Shared {
+    master: Mutex<Master>,
+    /// Process data path of each group (by group ID)
+    ///
+    /// It's `None` if the group was not mapped.
+    groups: Vec<Option<Mutex<GroupIo>>>,
+    pdos: Vec<PdoInfo>,
+    slave_count: usize,
+}
+
+/// Process data exchange of a group after splitting the master
+struct GroupIo {
+    pd: ctx::ProcessData,
+    wkc: WkcCheck,
+    events: Sender<Event>,
+}
+
+impl GroupIo {
+    fn send(&mut self) -> Result<()> {
+        self.pd.send();
+        if self.pd.is_err() {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::SendProcessData);
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<usize> {
+        let wkc = self.pd.receive(DEFAULT_RECV_TIMEOUT);
+        if self.pd.is_err() {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::RecvProcessData);
+        }
+        let wkc = wkc.max(0) as usize;
+        self.check_wkc(wkc);
+        Ok(wkc)
+    }
+
+    fn check_wkc(&mut self, wkc: usize) {
+        if let Some(event) = self.wkc.update(self.pd.group(), wkc) {
+            // The mailbox handle might be gone already.
+            let _ = self.events.send(event);
+        }
+    }
+
+    fn ctx_errors(&mut self) -> Vec<ctx::Error> {
+        let mut errors = vec![];
+        while let Some(e) = self.pd.pop_error() {
+            errors.push(e);
+        }
+        errors
+    }
+}
+
+pub(crate) fn split(mut master: Master) -> (CyclicHandle, MailboxHandle) {
+    let (tx, rx) = mpsc::channel();
+    let groups = (0..master.max_group())
+        .map(|g| {
+            // Only mapped groups have an expected working counter.
+            // SOEM maps all slaves into group 0, so taking it
+            // would leave nothing for the groups that are used.
+            master.wkc[g].expected?;
+            // The context is kept alive by `Shared` and is never
+            // initialised again, so the process data can refer to it.
+            let pd = unsafe {
+                master
+                    .ctx
+                    .take_process_data(g as u8, master.overlap_mapping)
+            };
+            Some(Mutex::new(GroupIo {
+                pd,
+                wkc: master.wkc[g],
+                events: tx.clone(),
+            }))
+        })
+        .collect();
+    master.cyclic_events = Some(rx);
+    let shared = Arc::new(Shared {
+        pdos: master.pdos.clone(),
+        slave_count: master.slave_count(),
+        groups,
+        master: Mutex::new(master),
+    });
+    let cyclic = CyclicHandle {
+        shared: Arc::clone(&shared),
+    };
+    let mailbox = MailboxHandle { shared };
+    (cyclic, mailbox)
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding the lock does not leave the
+    // SOEM context in an inconsistent state.
+    m.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Handle for the cyclic process data exchange
+///
+/// Created by [`Master::split`].
+///
+/// Each group has its own lock, so the handle can be cloned
+/// to exchange the process data of different groups
+/// with different cycle times in different threads.
+#[derive(Clone)]
+#[allow(missing_debug_implementations)]
+pub struct CyclicHandle {
+    shared: Arc<Shared>,
+}
+
+impl CyclicHandle {
+    fn group(&self, group: u8) -> Result<MutexGuard<'_, GroupIo>> {
+        self.shared
+            .groups
+            .get(group as usize)
+            .and_then(Option::as_ref)
+            .map(lock)
+            .ok_or(Error::GroupId)
+    }
+
+    pub fn send_processdata(&self) -> Result<()> {
+        self.send_group_processdata(0)
+    }
+
+    pub fn recv_processdata(&self) -> Result<usize> {
+        self.recv_group_processdata(0)
+    }
+
+    pub fn send_group_processdata(&self, group: u8) -> Result<()> {
+        self.group(group)?.send()
+    }
+
+    pub fn recv_group_processdata(&self, group: u8) -> Result<usize> {
+        self.group(group)?.recv()
+    }
+
+    /// Run the process data exchange with the given [`CycleRunner`].
+    ///
+    /// The group is only locked while exchanging the data
+    /// and running `f`, not while waiting for the next cycle.
+    pub fn run<F>(&self, runner: &mut CycleRunner, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut ProcessImage<'_>, &Cycle) -> ControlFlow<()>,
+    {
+        let group = runner.group();
+        runner.run_with(|index, latency| {
+            let mut io = self.group(group)?;
+            io.send()?;
+            let wkc = io.recv()?;
+            let cycle = Cycle {
+                index,
+                wkc,
+                latency,
+            };
+            let mut image = ProcessImage {
+                io: &mut io,
+                pdos: &self.shared.pdos,
+            };
+            let flow = f(&mut image, &cycle);
+            Ok((flow, io.pd.dc_time()))
+        })
+    }
+
+    /// Access the process data of a group while holding its lock.
+    pub fn with_group<F, T>(&self, group: u8, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut ProcessImage<'_>) -> T,
+    {
+        let mut io = self.group(group)?;
+        Ok(f(&mut ProcessImage {
+            io: &mut io,
+            pdos: &self.shared.pdos,
+        }))
+    }
+
+    #[must_use]
+    pub fn expected_wkc(&self, group: u8) -> Option<usize> {
+        self.group(group).ok().and_then(|io| io.wkc.expected)
+    }
+
+    /// The PDO values of all slaves.
+    ///
+    /// The groups are locked one after the other.
+    #[must_use]
+    pub fn pdo_values(&self) -> Vec<Vec<(ec::Idx, Vec<ec::Value>)>> {
+        let mut all_pdos = vec![vec![]; self.shared.slave_count];
+        for io in self.shared.groups.iter().flatten() {
+            let mut io = lock(io);
+            let image = ProcessImage {
+                io: &mut io,
+                pdos: &self.shared.pdos,
+            };
+            for (i, values) in all_pdos.iter_mut().enumerate() {
+                let slave = ec::SlavePos::from(i as u16);
+                if image.contains(slave) {
+                    *values = image.slave_pdo_values(slave);
+                }
+            }
+        }
+        all_pdos
+    }
+
+    pub fn set_pdo_value(
+        &self,
+        slave: ec::SlavePos,
+        idx: ec::PdoEntryIdx,
+        v: ec::Value,
+    ) -> Result<()> {
+        for io in self.shared.groups.iter().flatten() {
+            let mut io = lock(io);
+            let mut image = ProcessImage {
+                io: &mut io,
+                pdos: &self.shared.pdos,
+            };
+            if image.contains(slave) {
+                return image.set_pdo_value(slave, idx, v);
+            }
+        }
+        Err(Error::SlaveNotFound(slave))
+    }
+
+    /// The DC time of the most recent exchange of any group.
+    #[must_use]
+    pub fn dc_time(&self) -> i64 {
+        self.shared
+            .groups
+            .iter()
+            .flatten()
+            .map(|io| lock(io).pd.dc_time())
+            .max()
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn slave_count(&self) -> usize {
+        self.shared.slave_count
+    }
+}
+
+/// Process data of a group
+///
+/// It's passed to the callback of [`CyclicHandle::run`]
+/// and only gives access to the process data, so the callback
+/// can't start mailbox transfers that would delay the cycle.
+#[allow(missing_debug_implementations)]
+pub struct ProcessImage<'a> {
+    io: &'a mut GroupIo,
+    pdos: &'a [PdoInfo],
+}
+
+impl ProcessImage<'_> {
+    #[must_use]
+    pub fn group(&self) -> u8 {
+        self.io.pd.group()
+    }
+
+    /// Returns `true` if the process data of the slave belongs to this group.
+    #[must_use]
+    pub fn contains(&self, slave: ec::SlavePos) -> bool {
+        self.io.pd.contains(u16::from(slave) + 1)
+    }
+
+    #[must_use]
+    pub fn inputs(&self, slave: ec::SlavePos) -> &[u8] {
+        self.io.pd.inputs(u16::from(slave) + 1)
+    }
+
+    #[must_use]
+    pub fn outputs(&self, slave: ec::SlavePos) -> &[u8] {
+        self.io.pd.outputs(u16::from(slave) + 1)
+    }
+
+    pub fn outputs_mut(&mut self, slave: ec::SlavePos) -> &mut [u8] {
+        self.io.pd.outputs_mut(u16::from(slave) + 1)
+    }
+
+    /// The PDO values of a slave of this group.
+    #[must_use]
+    pub fn slave_pdo_values(&self, slave: ec::SlavePos) -> Vec<(ec::Idx, Vec<ec::Value>)> {
+        match self.pdos.get(usize::from(slave)) {
+            Some(pdos) => pdo::pdo_values(pdos, self.inputs(slave), self.outputs(slave)),
+            None => vec![],
+        }
+    }
+
+    pub fn set_pdo_value(
+        &mut self,
+        slave: ec::SlavePos,
+        idx: ec::PdoEntryIdx,
+        v: ec::Value,
+    ) -> Result<()> {
+        if !self.contains(slave) {
+            return Err(Error::SlaveNotFound(slave));
+        }
+        let pdos = self
+            .pdos
+            .get(usize::from(slave))
+            .ok_or(Error::SlaveNotFound(slave))?;
+        pdo::set_pdo_value(pdos, self.io.pd.outputs_mut(u16::from(slave) + 1), idx, v)
+    }
+
+    #[must_use]
+    pub fn dc_time(&self) -> i64 {
+        self.io.pd.dc_time()
+    }
+
+    #[must_use]
+    pub fn expected_wkc(&self) -> Option<usize> {
+        self.io.wkc.expected
+    }
+}
+
+/// Handle for mailbox communication and state requests
+///
+/// Created by [`Master::split`].
+///
+/// Each call locks the shared context for its whole duration.
+/// The process data is exchanged independently of this lock,
+/// so long running transfers don't delay the cycles.
+#[allow(missing_debug_implementations)]
+pub struct MailboxHandle {
+    shared: Arc<Shared>,
+}
+
+impl MailboxHandle {
+    pub fn request_states(&self, state: ec::AlState) -> Result<()> {
+        lock(&self.shared.master).request_states(state)
+    }
+
+    pub fn check_states(&self, state: ec::AlState, timeout: Duration) -> Result<ec::AlState> {
+        lock(&self.shared.master).check_states(state, timeout)
+    }
+
+    pub fn states(&self) -> Result<Vec<ec::AlState>> {
+        lock(&self.shared.master).states()
+    }
+
+    /// See [`Master::check_slaves`].
+    pub fn check_slaves(&self) -> Result<()> {
+        lock(&self.shared.master).check_slaves()
+    }
+
+    /// See [`Master::supervise`].
+    pub fn supervise(&self, cfg: &RecoveryConfig) -> Result<()> {
+        lock(&self.shared.master).supervise(cfg)
+    }
+
+    /// See [`Master::redundancy_status`].
+    pub fn redundancy_status(&self) -> Result<RedundancyStatus> {
+        lock(&self.shared.master).redundancy_status()
+    }
+
+    #[must_use]
+    pub fn pop_event(&self) -> Option<Event> {
+        lock(&self.shared.master).pop_event()
+    }
+
+    #[must_use]
+    pub fn slave_count(&self) -> usize {
+        lock(&self.shared.master).slave_count()
+    }
+
+    /// Access the slaves while holding the lock.
+    ///
+    /// The process data belongs to the [`CyclicHandle`],
+    /// so the inputs and outputs of the slaves are empty.
+    pub fn with_slaves<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&[ctx::Slave]) -> T,
+    {
+        f(lock(&self.shared.master).slaves())
+    }
+
+    pub fn read_od_list(&self, slave: ec::SlavePos) -> Result<SdoInfo> {
+        lock(&self.shared.master).read_od_list(slave)
+    }
+
+    pub fn read_sdo<'t>(
+        &self,
+        slave: ec::SlavePos,
+        idx: ec::SdoIdx,
+        access_complete: bool,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> Result<&'t mut [u8]> {
+        lock(&self.shared.master).read_sdo(slave, idx, access_complete, target, timeout)
+    }
+
+    pub fn read_sdo_entry(
+        &self,
+        slave: ec::SlavePos,
+        idx: ec::SdoIdx,
+        timeout: Duration,
+    ) -> Result<ec::Value> {
+        lock(&self.shared.master).read_sdo_entry(slave, idx, timeout)
+    }
+
+    pub fn read_sdo_complete(
+        &self,
+        slave: ec::SlavePos,
+        idx: ec::Idx,
+        timeout: Duration,
+    ) -> Result<Vec<Option<ec::Value>>> {
+        lock(&self.shared.master).read_sdo_complete(slave, idx, timeout)
+    }
+
+    pub fn write_sdo(
+        &self,
+        slave: ec::SlavePos,
+        idx: ec::SdoIdx,
+        access_complete: bool,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<()> {
+        lock(&self.shared.master).write_sdo(slave, idx, access_complete, data, timeout)
+    }
+
+    pub fn write_sdo_entry(
+        &self,
+        slave: ec::SlavePos,
+        idx: ec::SdoIdx,
+        value: ec::Value,
+        timeout: Duration,
+    ) -> Result<()> {
+        lock(&self.shared.master).write_sdo_entry(slave, idx, value, timeout)
+    }
+
+    pub fn foe_read<'t>(
+        &self,
+        slave: ec::SlavePos,
+        filename: &str,
+        password: u32,
+        target: &'t mut [u8],
+    ) -> Result<&'t mut [u8]> {
+        lock(&self.shared.master).foe_read(slave, filename, password, target)
+    }
+
+    pub fn foe_write(
+        &self,
+        slave: ec::SlavePos,
+        filename: &str,
+        password: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        lock(&self.shared.master).foe_write(slave, filename, password, data)
+    }
+
+    pub fn read_idn<'t>(
+        &self,
+        slave: ec::SlavePos,
+        drive_no: u8,
+        elements: IdnElements,
+        idn: u16,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> Result<&'t mut [u8]> {
+        lock(&self.shared.master).read_idn(slave, drive_no, elements, idn, target, timeout)
+    }
+
+    pub fn write_idn(
+        &self,
+        slave: ec::SlavePos,
+        drive_no: u8,
+        elements: IdnElements,
+        idn: u16,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<()> {
+        lock(&self.shared.master).write_idn(slave, drive_no, elements, idn, data, timeout)
+    }
+
+    pub fn eoe_set_ip(&self, slave: ec::SlavePos, port: u8, param: &EoeIpParam) -> Result<()> {
+        lock(&self.shared.master).eoe_set_ip(slave, port, param)
+    }
+
+    /// Forward the pending frames of an EoE bridge.
+    #[cfg(target_os = "linux")]
+    pub fn poll_eoe_bridge(&self, bridge: &mut EoeBridge) -> Result<()> {
+        bridge.poll(&mut lock(&self.shared.master))
+    }
+
+    /// See [`Master::poll_emergencies`].
+    pub fn poll_emergencies(&self) {
+        lock(&self.shared.master).poll_emergencies();
+    }
+
+    #[must_use]
+    pub fn subscribe_emergencies(&self) -> Receiver<Emergency> {
+        lock(&self.shared.master).subscribe_emergencies()
+    }
+
+    #[must_use]
+    pub fn emergency_history(&self, slave: ec::SlavePos) -> Vec<Emergency> {
+        lock(&self.shared.master).emergency_history(slave)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forward_wkc_events_to_the_mailbox_handle() {
+        let mut master = Master::with_ctx(Box::new(ctx::Ctx::default()));
+        master.wkc[1].expected = Some(3);
+        let (cyclic, mailbox) = master.split();
+        assert_eq!(cyclic.expected_wkc(1), Some(3));
+        assert_eq!(cyclic.expected_wkc(9), None);
+
+        cyclic.group(1).unwrap().check_wkc(2);
+        cyclic.group(1).unwrap().check_wkc(2);
+        assert_eq!(
+            mailbox.pop_event(),
+            Some(Event::WkcMismatch {
+                group: 1,
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(mailbox.pop_event(), None);
+        assert!(lock(&mailbox.shared.master).wkc[1].mismatch);
+
+        cyclic.group(1).unwrap().check_wkc(3);
+        assert_eq!(mailbox.pop_event(), Some(Event::WkcRestored { group: 1 }));
+        assert!(!lock(&mailbox.shared.master).wkc[1].mismatch);
+    }
+
+    #[test]
+    fn lock_the_groups_independently() {
+        let ctx = ctx::CtxBuilder::new().max_groups(3).build();
+        let mut master = Master::with_ctx(Box::new(ctx));
+        master.wkc[1].expected = Some(2);
+        master.wkc[2].expected = Some(4);
+        let (cyclic, mailbox) = master.split();
+        assert!(matches!(cyclic.group(0), Err(Error::GroupId)));
+        let _group_1 = cyclic.group(1).unwrap();
+        let _master = lock(&mailbox.shared.master);
+        let other = cyclic.clone();
+        let expected = std::thread::spawn(move || other.expected_wkc(2))
+            .join()
+            .unwrap();
+        assert_eq!(expected, Some(4));
+    }
+
+    #[test]
+    fn exchange_the_process_data_of_two_groups() {
+        let mut ctx = ctx::CtxBuilder::new().max_groups(3).build();
+        ctx.fake_map_group(1, vec![1, 2, 3], &[(1, 2..3, 0..2)]);
+        ctx.fake_map_group(2, vec![4, 5], &[(2, 0..0, 0..1), (3, 1..2, 0..0)]);
+        let mut master = Master::with_ctx(Box::new(ctx));
+        master.wkc[1].expected = Some(3);
+        master.wkc[2].expected = Some(3);
+        let (cyclic, _mailbox) = master.split();
+        let [s0, s1, s2] = [0, 1, 2].map(ec::SlavePos::from);
+
+        cyclic
+            .with_group(1, |image| {
+                assert_eq!(image.group(), 1);
+                assert!(image.contains(s0));
+                assert!(!image.contains(s1));
+                assert_eq!(image.inputs(s0), &[3]);
+                image.outputs_mut(s0).copy_from_slice(&[7, 8]);
+            })
+            .unwrap();
+        cyclic
+            .with_group(2, |image| {
+                assert!(!image.contains(s0));
+                assert!(image.outputs_mut(s0).is_empty());
+                assert_eq!(image.inputs(s2), &[5]);
+                image.outputs_mut(s1)[0] = 9;
+            })
+            .unwrap();
+
+        let outputs = cyclic.with_group(1, |image| image.outputs(s0).to_vec());
+        assert_eq!(outputs.unwrap(), [7, 8]);
+        let outputs = cyclic.with_group(2, |image| image.outputs(s1).to_vec());
+        assert_eq!(outputs.unwrap(), [9]);
+        assert!(cyclic.with_group(0, |_| ()).is_err());
+    }
+}
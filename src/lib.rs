@@ -16,13 +16,71 @@
 use ethercat_soem_ctx as ctx;
 use ethercat_types as ec;
 use num_traits::cast::FromPrimitive;
-use std::{convert::TryFrom, ffi::CString, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    ffi::CString,
+    sync::{mpsc::Receiver, Arc, Mutex, PoisonError},
+    time::Duration,
+};
 
 mod al_status;
+mod config;
+mod cycle;
+mod dc;
+mod emergency;
+#[cfg(feature = "eni")]
+mod eni;
+mod eoe;
 mod error;
+mod esi;
+mod event;
+mod foe;
+mod handle;
+mod pdo;
+mod redundancy;
+mod sdo_abort;
+mod sii;
+mod soe;
+mod startup;
+mod supervision;
+mod topology;
 mod util;
-
-pub use self::{al_status::*, error::Error};
+#[cfg(any(feature = "eni", feature = "esi"))]
+mod xml;
+
+pub use ethercat_soem_ctx::{CtxBuilder, EoeIpParam};
+
+pub use self::{
+    al_status::*,
+    config::{
+        PdoConfig, PdoEntryConfig, SlaveIdentity, SlavePdoConfig, SlaveSelector, SmPdoConfig,
+    },
+    cycle::{Cycle, CycleRunner, CycleStats, TimeStats},
+    dc::{DcDriftController, DcSync},
+    emergency::{error_code_description, Emergency},
+    error::Error,
+    esi::{Esi, EsiDevice},
+    event::Event,
+    handle::{CyclicHandle, MailboxHandle, ProcessImage},
+    pdo::{PdoDescription, PdoEntryDescription},
+    redundancy::{Port, RedundancyStatus},
+    sdo_abort::SdoAbortCode,
+    sii::{Sii, SiiDcSyncMode, SiiFmmu, SiiGeneral, SiiSm},
+    soe::IdnElements,
+    startup::{StartupSdo, StartupSdoError, StartupSdoErrorKind},
+    supervision::RecoveryConfig,
+    topology::{ExpectedSlave, TopologyMismatch},
+};
+
+#[cfg(feature = "eni")]
+pub use self::eni::{Eni, EniSlave};
+#[cfg(target_os = "linux")]
+pub use self::eoe::{EoeBridge, TapDevice};
+#[cfg(any(feature = "eni", feature = "esi"))]
+pub use self::xml::XmlError;
+
+use self::supervision::{SlaveReport, WkcCheck};
 
 const DEFAULT_RECV_TIMEOUT: Duration = Duration::from_micros(2_000);
 const DEFAULT_SDO_TIMEOUT: Duration = Duration::from_millis(3_000);
@@ -32,6 +90,9 @@ const MAX_SM_CNT: u8 = 8;
 const SDO_IDX_PDO_ASSIGN: u16 = 0x1C10;
 const SDO_IDX_SM_COMM_TYPE: ec::Idx = ec::Idx::new(0x1C00);
 
+const COE_DETAILS_SDO_INFO: u8 = 0x02;
+pub(crate) const MBX_PROTO_COE: u16 = 0x0004;
+
 const EC_NOFRAME: i32 = -1;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -57,63 +118,142 @@ pub struct Master {
     ctx: Box<ctx::Ctx>,
     sdos: Vec<SdoInfo>,
     pdos: Vec<PdoInfo>,
+    wkc: Vec<WkcCheck>,
+    events: VecDeque<Event>,
+    slave_reports: HashMap<u16, SlaveReport>,
+    io_map_capacity: Option<usize>,
+    overlap_mapping: bool,
+    pdo_configs: HashMap<u16, SlavePdoConfig>,
+    pdo_config_errors: Arc<Mutex<Vec<Error>>>,
+    startup_sdos: Vec<(SlaveSelector, Vec<StartupSdo>)>,
+    startup_sdo_errors: Arc<Mutex<Vec<StartupSdoError>>>,
+    expected_topology: Option<Vec<ExpectedSlave>>,
+    dc_syncs: HashMap<u16, DcSync>,
+    topology_mismatches: Vec<TopologyMismatch>,
+    esi: Esi,
+    eoe_frames: eoe::EoeFrames,
+    emergencies: emergency::Emergencies,
+    /// Working counter events of the process data paths after [`Master::split`]
+    cyclic_events: Option<Receiver<Event>>,
 }
 
 impl Master {
     pub fn try_new<S: Into<String>>(iface: S) -> Result<Self> {
-        let mut master = Self {
-            ctx: Box::new(ctx::Ctx::default()),
-            sdos: vec![],
-            pdos: vec![],
-        };
+        Self::try_new_with_ctx(iface, ctx::Ctx::default())
+    }
+
+    /// Create a master with a custom context,
+    /// e.g. to change the maximum number of slaves or groups:
+    ///
+    /// ```rust,no_run
+    /// use ethercat_soem::{CtxBuilder, Master};
+    ///
+    /// let ctx = CtxBuilder::new().max_slaves(16).build();
+    /// let master = Master::try_new_with_ctx("eth0", ctx);
+    /// ```
+    pub fn try_new_with_ctx<S: Into<String>>(iface: S, ctx: ctx::Ctx) -> Result<Self> {
+        let mut master = Self::with_ctx(Box::new(ctx));
         master.init(iface.into())?;
         Ok(master)
     }
 
-    #[doc(hidden)]
-    /// Don't use this!
-    pub fn ptr(&mut self) -> *mut ctx::Ctx {
-        let reference: &mut ctx::Ctx = &mut *self.ctx;
-        reference as *mut ctx::Ctx
+    /// Create a master in cable redundancy mode.
+    ///
+    /// The slaves have to be connected as a ring
+    /// from the `primary` to the `secondary` interface.
+    pub fn try_new_redundant<S: Into<String>>(primary: S, secondary: S) -> Result<Self> {
+        Self::try_new_redundant_with_ctx(primary, secondary, ctx::Ctx::default())
     }
 
-    #[doc(hidden)]
-    /// Don't use this!
-    pub fn io_map(&mut self) -> &mut [u8] {
-        &mut self.ctx.io_map
+    /// Create a master in cable redundancy mode with a custom context.
+    pub fn try_new_redundant_with_ctx<S: Into<String>>(
+        primary: S,
+        secondary: S,
+        ctx: ctx::Ctx,
+    ) -> Result<Self> {
+        let mut master = Self::with_ctx(Box::new(ctx));
+        master.init_redundant(primary.into(), secondary.into())?;
+        Ok(master)
     }
 
-    #[doc(hidden)]
-    /// Don't use this!
-    pub unsafe fn from_ptr(ctx_ptr: *mut ctx::Ctx) -> Self {
-        Master::from_ptr_with_caches(ctx_ptr, vec![], vec![])
+    fn with_ctx(mut ctx: Box<ctx::Ctx>) -> Self {
+        let eoe_frames = eoe::register_eoe_hook(&mut ctx);
+        let wkc = vec![WkcCheck::default(); ctx.max_group() as usize];
+        Self {
+            ctx,
+            sdos: vec![],
+            pdos: vec![],
+            wkc,
+            events: VecDeque::new(),
+            slave_reports: HashMap::new(),
+            io_map_capacity: None,
+            overlap_mapping: false,
+            pdo_configs: HashMap::new(),
+            pdo_config_errors: Arc::new(Mutex::new(vec![])),
+            startup_sdos: vec![],
+            startup_sdo_errors: Arc::new(Mutex::new(vec![])),
+            expected_topology: None,
+            dc_syncs: HashMap::new(),
+            topology_mismatches: vec![],
+            esi: Esi::default(),
+            eoe_frames,
+            emergencies: emergency::Emergencies::default(),
+            cyclic_events: None,
+        }
+    }
+
+    /// Split the master into a handle for the cyclic process data exchange
+    /// and a handle for mailbox communication.
+    ///
+    /// Both handles share the same SOEM context and can be moved to different threads.
+    /// Each mapped group gets its own process data path with its own I/O map,
+    /// so neither mailbox transfers nor the exchange of other groups
+    /// block the cyclic exchange of a group.
+    /// The process data has to be mapped before splitting the master.
+    /// If the slaves are assigned to groups, group 0 is not mapped
+    /// and can't be used with the [`CyclicHandle`].
+    #[must_use]
+    pub fn split(self) -> (CyclicHandle, MailboxHandle) {
+        handle::split(self)
     }
 
     #[doc(hidden)]
     /// Don't use this!
-    pub unsafe fn from_ptr_with_caches(
-        ctx_ptr: *mut ctx::Ctx,
-        sdos: Vec<SdoInfo>,
-        pdos: Vec<PdoInfo>,
-    ) -> Self {
-        Master {
-            ctx: Box::from_raw(ctx_ptr),
-            sdos,
-            pdos,
-        }
+    pub fn io_map(&mut self) -> &mut [u8] {
+        self.ctx.io_map_mut(0)
     }
 
-    #[doc(hidden)]
-    /// Drop a secondary instance safely
+    /// Limit the size of the I/O map of each group.
     ///
-    /// Drop an instance that has been created with `from_ptr` or `from_ptr_with_caches`
-    /// from a shared context pointer that is dropped later. Otherwise dropping this
-    /// instance would result in a double-free.
+    /// The memory is reserved in advance, so it does not
+    /// need to be allocated while mapping the process data.
+    /// If the process image of a group does not fit,
+    /// [`Master::auto_config`] fails with [`Error::IoMapSize`].
+    pub fn set_io_map_capacity(&mut self, capacity: usize) {
+        for group in 0..self.max_group() {
+            self.ctx.reserve_io_map(group as u8, capacity);
+        }
+        self.io_map_capacity = Some(capacity);
+    }
+
+    #[must_use]
+    pub const fn io_map_capacity(&self) -> Option<usize> {
+        self.io_map_capacity
+    }
+
+    /// Map inputs and outputs of the slaves to the same logical
+    /// address range, so that a single LRW datagram serves both.
     ///
-    /// TODO: Avoid all these ugly hacks!
-    pub fn leak_ptr(self) {
-        let Self { ctx, .. } = self;
-        Box::leak(ctx);
+    /// This reduces the frame size but has to be supported by the slaves.
+    /// It must be set before the process data is mapped
+    /// and must not be changed afterwards.
+    pub fn set_overlap_mapping(&mut self, overlap: bool) {
+        self.overlap_mapping = overlap;
+    }
+
+    #[must_use]
+    pub const fn overlap_mapping(&self) -> bool {
+        self.overlap_mapping
     }
 
     #[doc(hidden)]
@@ -141,8 +281,36 @@ impl Master {
         Ok(())
     }
 
+    fn init_redundant(&mut self, primary: String, secondary: String) -> Result<()> {
+        log::debug!(
+            "Initialise SOEM stack in redundant mode: bind sockets to {} and {}",
+            primary,
+            secondary
+        );
+        let primary = CString::new(primary).map_err(|_| Error::Iface)?;
+        let secondary = CString::new(secondary).map_err(|_| Error::Iface)?;
+        let res = self.ctx.init_redundant(primary, secondary);
+        if res <= 0 {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::Init);
+        }
+        Ok(())
+    }
+
     /// Automatically configure slaves and fetch SDO & PDO information.
+    ///
+    /// This is a shortcut for [`Master::config_init`]
+    /// followed by [`Master::config_map`].
     pub fn auto_config(&mut self) -> Result<()> {
+        self.config_init()?;
+        self.config_map()
+    }
+
+    /// Find the slaves and configure the distributed clocks.
+    ///
+    /// Afterwards the slaves can be assigned to groups
+    /// with [`Master::set_slave_group`].
+    pub fn config_init(&mut self) -> Result<()> {
         log::debug!("Find and auto-config slaves");
         let usetable = false;
         let res = self.ctx.config_init(usetable);
@@ -151,26 +319,213 @@ impl Master {
             return Err(match res {
                 -1 => Error::NoFrame,
                 -2 => Error::OtherFrame,
+                ctx::EC_SLAVECOUNTEXCEEDED => Error::TooManySlaves,
                 _ => Error::NoSlaves,
             });
         }
         let slave_count = self.ctx.slave_count();
         log::debug!("{} slaves found", slave_count);
+        self.check_topology()?;
         let res = self.ctx.config_dc();
         if res == 0 {
             log::debug!("Context errors: {:?}", self.ctx_errors());
             return Err(Error::CfgDc);
         }
-        let group = 0;
-        let io_map_size = self.ctx.config_map_group(group);
+        Ok(())
+    }
+
+    /// Assign a slave to a process data group.
+    ///
+    /// Group `0` contains all slaves.
+    /// As soon as one slave is assigned to another group,
+    /// all slaves have to be assigned to a group `> 0`,
+    /// otherwise [`Master::config_map`] fails with [`Error::UngroupedSlave`].
+    /// This is because SOEM maps all slaves into the I/O map of group `0`,
+    /// so they would be exchanged twice.
+    ///
+    /// After [`Master::split`] each group has its own process data path,
+    /// so groups with different cycle times can be exchanged
+    /// in different threads without delaying each other.
+    pub fn set_slave_group(&mut self, slave: ec::SlavePos, group: u8) -> Result<()> {
+        if group as usize >= self.max_group() {
+            return Err(Error::GroupId);
+        }
+        let s = self
+            .slaves_mut()
+            .get_mut(usize::from(slave))
+            .ok_or(Error::SlaveNotFound(slave))?;
+        s.set_group(group);
+        Ok(())
+    }
+
+    /// Set the PDO configuration of a slave.
+    ///
+    /// It's written in PRE-OP before the process data is mapped
+    /// and again if the slave is reconfigured.
+    pub fn set_pdo_config(&mut self, slave: ec::SlavePos, cfg: SlavePdoConfig) {
+        self.pdo_configs.insert(u16::from(slave), cfg);
+    }
+
+    /// Map the process data of all groups,
+    /// activate the DC SYNC signals and fetch SDO & PDO information.
+    ///
+    /// The startup SDOs and the PDO configurations are written
+    /// while the process data is mapped.
+    /// If slaves are assigned to groups (see [`Master::set_slave_group`]),
+    /// each group gets its own I/O map.
+    pub fn config_map(&mut self) -> Result<()> {
+        if !self.pdo_configs.is_empty() || !self.startup_sdos.is_empty() {
+            self.register_config_hook();
+        }
+        // Errors of earlier mappings or reconfigurations are outdated.
+        self.take_config_errors();
+        for group in self.groups_to_map()? {
+            self.map_group(group)?;
+        }
+        let (startup_sdos, pdo_configs) = self.take_config_errors();
+        self.record_config_emergencies(&startup_sdos, &pdo_configs);
+        if !startup_sdos.is_empty() {
+            return Err(Error::StartupSdos(startup_sdos));
+        }
+        if !pdo_configs.is_empty() {
+            return Err(Error::PdoConfig(pdo_configs));
+        }
+        self.activate_dc_syncs()?;
+        self.scan_slave_objects()?;
+        self.pdos = self.coe_pdo_info()?;
+        Ok(())
+    }
+
+    /// Write the startup SDOs and the PDO configuration of each slave
+    /// in the transition PRE-OP -> SAFE-OP.
+    fn register_config_hook(&mut self) {
+        let startup_sdos = self.slave_startup_sdos();
+        let startup_sdo_errors = Arc::clone(&self.startup_sdo_errors);
+        let configs = self.pdo_configs.clone();
+        let errors = Arc::clone(&self.pdo_config_errors);
+        self.ctx.set_config_hook(move |ctx, slave| {
+            if let Some(sdos) = startup_sdos.get(&slave) {
+                let failed = startup::write_startup_sdos(ctx, slave, sdos);
+                startup_sdo_errors
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .extend(failed);
+            }
+            let cfg = match configs.get(&(slave - 1)) {
+                Some(cfg) => cfg,
+                None => return 1,
+            };
+            match config::write_pdo_config(ctx, slave, cfg) {
+                Ok(()) => 1,
+                Err(idx) => {
+                    let pos = ec::SlavePos::new(slave - 1);
+                    log::warn!("Could not write PDO config {:?} of {:?}", idx, pos);
+                    let ctx_errors = startup::ctx_errors(ctx);
+                    errors
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(Error::write_sdo(pos, idx, ctx_errors));
+                    0
+                }
+            }
+        });
+    }
+
+    /// The config hook can't record the emergencies
+    /// that SOEM received with its transfers.
+    pub(crate) fn record_config_emergencies(
+        &mut self,
+        startup_sdos: &[StartupSdoError],
+        pdo_configs: &[Error],
+    ) {
+        let startup_errors = startup_sdos.iter().flat_map(|e| match &e.kind {
+            StartupSdoErrorKind::Write { errors, .. }
+            | StartupSdoErrorKind::Read { errors, .. } => errors.as_slice(),
+            StartupSdoErrorKind::Mismatch { .. } => &[],
+        });
+        let pdo_config_errors = pdo_configs.iter().flat_map(|e| match e {
+            Error::WriteSdo { errors, .. } => errors.as_slice(),
+            _ => &[],
+        });
+        let emergencies: Vec<_> = startup_errors
+            .chain(pdo_config_errors)
+            .filter(|e| e.err_type == ctx::ErrType::Emergency)
+            .cloned()
+            .collect();
+        for e in &emergencies {
+            self.record_emergency(e);
+        }
+    }
+
+    /// Take the startup SDO and PDO configuration errors of the config hook.
+    pub(crate) fn take_config_errors(&mut self) -> (Vec<StartupSdoError>, Vec<Error>) {
+        let startup_sdos = std::mem::take(
+            &mut *self
+                .startup_sdo_errors
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        let pdo_configs = std::mem::take(
+            &mut *self
+                .pdo_config_errors
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        (startup_sdos, pdo_configs)
+    }
+
+    fn groups_to_map(&self) -> Result<Vec<u8>> {
+        let mut groups: Vec<u8> = self.slaves().iter().map(ctx::Slave::group).collect();
+        groups.sort_unstable();
+        groups.dedup();
+        if groups.is_empty() || groups == [0] {
+            return Ok(vec![0]);
+        }
+        // SOEM maps all slaves into group 0,
+        // so it can't be combined with other groups.
+        if let Some(i) = self.slaves().iter().position(|s| s.group() == 0) {
+            return Err(Error::UngroupedSlave(ec::SlavePos::from(i as u16)));
+        }
+        Ok(groups)
+    }
+
+    fn map_group(&mut self, group: u8) -> Result<()> {
+        let io_map_size = if self.overlap_mapping {
+            self.ctx.config_overlap_map_group(group)
+        } else {
+            self.ctx.config_map_group(group)
+        };
         if io_map_size <= 0 {
             log::debug!("Context errors: {:?}", self.ctx_errors());
             return Err(Error::CfgMapGroup);
         }
-        let expected_wkc = self.group_outputs_wkc(0)? * 2 + self.group_inputs_wkc(0)?;
-        log::debug!("Expected working counter = {}", expected_wkc);
-        self.scan_slave_objects()?;
-        self.pdos = self.coe_pdo_info()?;
+        let io_map_size = io_map_size as usize;
+        log::debug!("Group {}: I/O map size = {} bytes", group, io_map_size);
+        if let Some(capacity) = self.io_map_capacity {
+            if io_map_size > capacity {
+                log::warn!(
+                    "Process image of group {} ({} bytes) does not fit into the I/O map ({} bytes)",
+                    group,
+                    io_map_size,
+                    capacity
+                );
+                return Err(Error::IoMapSize {
+                    required: io_map_size,
+                    capacity,
+                });
+            }
+        }
+        let i = group as usize;
+        let expected_wkc = self.group_outputs_wkc(i)? * 2 + self.group_inputs_wkc(i)?;
+        log::debug!(
+            "Group {}: expected working counter = {}",
+            group,
+            expected_wkc
+        );
+        self.wkc[i] = WkcCheck {
+            expected: Some(expected_wkc),
+            mismatch: false,
+        };
         Ok(())
     }
 
@@ -178,7 +533,20 @@ impl Master {
         log::debug!("Fetch SDO info");
         for i in 0..self.ctx.slave_count() {
             let pos = ec::SlavePos::from(i as u16);
-            let sdo_info = self.read_od_list(pos)?;
+            let sdo_info = match self.read_od_list(pos) {
+                Ok(info) => info,
+                Err(err) => match self.esi_device(pos) {
+                    Some(dev) => {
+                        log::debug!("{:?}: use the object dictionary of {}", pos, dev.name);
+                        dev.objects.clone()
+                    }
+                    None if self.slaves()[i].coe_details() & COE_DETAILS_SDO_INFO == 0 => {
+                        log::debug!("{:?} does not provide SDO information", pos);
+                        vec![]
+                    }
+                    None => return Err(err),
+                },
+            };
             self.sdos.push(sdo_info);
         }
         Ok(())
@@ -191,9 +559,29 @@ impl Master {
         for slave in 0..self.ctx.slave_count() as u16 {
             let slave_pos = ec::SlavePos::new(slave);
 
-            let obj_cnt = self.sm_comm_type_sdo(slave_pos, 0)?;
+            if self.slaves()[slave as usize].mbx_proto() & MBX_PROTO_COE == 0 {
+                log::debug!("{:?} does not support CoE", slave_pos);
+                let info = match self.soe_pdo_info(slave_pos) {
+                    Some(info) => Some(info),
+                    None => self.default_pdo_info(slave_pos),
+                };
+                res.push(info.unwrap_or_default());
+                continue;
+            }
+
+            let obj_cnt = match self.sm_comm_type_sdo(slave_pos, 0) {
+                Ok(cnt) => cnt,
+                Err(err) => match self.default_pdo_info(slave_pos) {
+                    Some(info) => {
+                        res.push(info);
+                        continue;
+                    }
+                    None => return Err(err),
+                },
+            };
             if obj_cnt <= 2 {
                 log::warn!("Slave {}: found less than two sync manager types", slave);
+                res.push(self.default_pdo_info(slave_pos).unwrap_or_default());
                 continue;
             }
 
@@ -269,6 +657,32 @@ impl Master {
         Ok(res)
     }
 
+    /// The PDOs that are assigned by default according to
+    /// the ESI description or the SII of a slave.
+    ///
+    /// If the slave has a PDO configuration,
+    /// the configured PDOs are used instead.
+    fn default_pdo_info(&mut self, slave: ec::SlavePos) -> Option<PdoInfo> {
+        let (source, pdos) = match self.esi_device(slave) {
+            Some(dev) => (Some(dev.name.clone()), dev.pdos.clone()),
+            None => match self.read_sii_info(slave) {
+                Ok(sii) if !sii.pdos.is_empty() => (Some("the SII".to_string()), sii.pdos),
+                Ok(_) => (None, vec![]),
+                Err(err) => {
+                    log::debug!("Could not read SII of {:?}: {}", slave, err);
+                    (None, vec![])
+                }
+            },
+        };
+        if let Some(cfg) = self.pdo_configs.get(&u16::from(slave)) {
+            log::debug!("{:?}: use the PDOs of its PDO configuration", slave);
+            return Some(pdo::default_pdo_info(&pdo::configured_pdos(cfg, &pdos)));
+        }
+        let source = source?;
+        log::debug!("{:?}: use the default PDOs of {}", slave, source);
+        Some(pdo::default_pdo_info(&pdos))
+    }
+
     /// Read PDO assign structure
     fn si_pdo_assign(
         &mut self,
@@ -350,10 +764,16 @@ impl Master {
                         debug_assert_eq!(bit_len as u16, e.bit_len);
                         (e.description.clone(), e.data_type)
                     }
-                    None => {
-                        log::warn!("Could not find SDO ({:?}) entry description", sdo);
-                        (String::new(), ec::DataType::Raw)
-                    }
+                    None => match self
+                        .esi_pdo(slave, pdo_idx.into())
+                        .and_then(|pdo| pdo.entries.iter().find(|e| e.sdo == sdo))
+                    {
+                        Some(e) => (e.name.clone(), e.data_type),
+                        None => {
+                            log::warn!("Could not find SDO ({:?}) entry description", sdo);
+                            (String::new(), ec::DataType::Raw)
+                        }
+                    },
                 };
                 let idx = ec::PdoEntryIdx::new(pdo_idx, entry_sub);
                 let pos = ec::PdoEntryPos::new(pdo_entry_pos);
@@ -383,10 +803,13 @@ impl Master {
 
                 let name = match sdo_info {
                     Some(info) => info.name.clone(),
-                    None => {
-                        log::warn!("Could not find SDO ({:?}) name", e.sdo.idx);
-                        String::new()
-                    }
+                    None => match self.esi_pdo(slave, pdo_idx.into()) {
+                        Some(pdo) => pdo.name.clone(),
+                        None => {
+                            log::warn!("Could not find SDO ({:?}) name", e.sdo.idx);
+                            String::new()
+                        }
+                    },
                 };
 
                 let pdo_info = ec::PdoInfo {
@@ -438,6 +861,10 @@ impl Master {
     }
 
     pub fn request_states(&mut self, state: ec::AlState) -> Result<()> {
+        if state == ec::AlState::Op && !self.topology_mismatches.is_empty() {
+            log::warn!("Refuse to request OP: the slaves do not match the expected topology");
+            return Err(Error::Topology(self.topology_mismatches.clone()));
+        }
         log::debug!("wait for all slaves to reach {:?} state", state);
         let s = u8::from(state) as u16;
         for i in 0..=self.ctx.slave_count() {
@@ -461,12 +888,15 @@ impl Master {
         if res == 0 {
             log::debug!("Context errors: {:?}", self.ctx_errors());
             log::warn!("Could not check state {:?} for slaves", state);
-            return Err(Error::CheckState);
+            return Err(Error::CheckState(self.al_status_errors()));
         }
-        let found_state = ec::AlState::try_from(res as u8).map_err(|_| {
-            log::warn!("Could not translate u16 `{}` into AlState", res);
-            Error::CheckState
-        })?;
+        let found_state = match ec::AlState::try_from(res as u8) {
+            Ok(state) => state,
+            Err(_) => {
+                log::warn!("Could not translate u16 `{}` into AlState", res);
+                return Err(Error::CheckState(self.al_status_errors()));
+            }
+        };
         if found_state != state {
             log::debug!(
                 "Current state {:?} does not match expected state {:?}",
@@ -477,6 +907,24 @@ impl Master {
         Ok(found_state)
     }
 
+    /// The AL status codes of the slaves that report an error.
+    pub(crate) fn al_status_errors(&mut self) -> Vec<(ec::SlavePos, AlStatus)> {
+        if self.ctx.read_state() <= 0 {
+            return vec![];
+        }
+        self.slaves()
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.al_status_code() != 0)
+            .map(|(i, s)| {
+                (
+                    ec::SlavePos::from(i as u16),
+                    AlStatus::from(s.al_status_code()),
+                )
+            })
+            .collect()
+    }
+
     #[must_use]
     pub fn slaves(&self) -> &[ctx::Slave] {
         let cnt = self.ctx.slave_count();
@@ -501,6 +949,20 @@ impl Master {
         Ok(states)
     }
 
+    /// Read the current AL status code of all slaves.
+    pub fn al_status_codes(&mut self) -> Result<Vec<AlStatus>> {
+        if self.ctx.read_state() <= 0 {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::ReadStates);
+        }
+        let codes = self
+            .slaves()
+            .iter()
+            .map(|s| AlStatus::from(s.al_status_code()))
+            .collect();
+        Ok(codes)
+    }
+
     fn slave_state(&self, slave: usize) -> Result<ec::AlState> {
         let s = &self.ctx.slaves()[slave];
         let state = s.state();
@@ -509,7 +971,23 @@ impl Master {
     }
 
     pub fn send_processdata(&mut self) -> Result<()> {
-        self.ctx.send_processdata();
+        self.send_group_processdata(0)
+    }
+
+    pub fn recv_processdata(&mut self) -> Result<usize> {
+        self.recv_group_processdata(0)
+    }
+
+    /// Send the process data of a single group.
+    pub fn send_group_processdata(&mut self, group: u8) -> Result<()> {
+        if group as usize >= self.max_group() {
+            return Err(Error::GroupId);
+        }
+        if self.overlap_mapping {
+            self.ctx.send_overlap_processdata_group(group);
+        } else {
+            self.ctx.send_processdata_group(group);
+        }
         if self.ctx.is_err() {
             log::debug!("Context errors: {:?}", self.ctx_errors());
             return Err(Error::SendProcessData);
@@ -517,13 +995,29 @@ impl Master {
         Ok(())
     }
 
-    pub fn recv_processdata(&mut self) -> Result<usize> {
-        let wkc = self.ctx.receive_processdata(DEFAULT_RECV_TIMEOUT);
+    /// Receive the process data of a single group.
+    ///
+    /// It returns the working counter.
+    pub fn recv_group_processdata(&mut self, group: u8) -> Result<usize> {
+        if group as usize >= self.max_group() {
+            return Err(Error::GroupId);
+        }
+        let wkc = self
+            .ctx
+            .receive_processdata_group(group, DEFAULT_RECV_TIMEOUT);
         if self.ctx.is_err() {
             log::debug!("Context errors: {:?}", self.ctx_errors());
             return Err(Error::RecvProcessData);
         }
-        Ok(wkc as usize)
+        let wkc = wkc.max(0) as usize;
+        self.check_wkc(group, wkc);
+        Ok(wkc)
+    }
+
+    /// Pop the oldest event
+    pub fn pop_event(&mut self) -> Option<Event> {
+        self.receive_cyclic_events();
+        self.events.pop_front()
     }
 
     pub fn group_outputs_wkc(&mut self, i: usize) -> Result<usize> {
@@ -549,6 +1043,11 @@ impl Master {
         self.ctx.slave_count() as usize
     }
 
+    #[must_use]
+    pub fn groups(&self) -> &[ctx::Group] {
+        &self.ctx.groups()[..self.max_group()]
+    }
+
     #[must_use]
     pub fn max_group(&self) -> usize {
         self.ctx.max_group() as usize
@@ -563,8 +1062,9 @@ impl Master {
         let res = self.ctx.read_od_description(item, od_list);
         let pos = ec::SdoPos::from(item);
         if res <= 0 {
-            log::debug!("Context errors: {:?}", self.ctx_errors());
-            return Err(Error::ReadOdDesc(pos));
+            let errs = self.ctx_errors();
+            log::debug!("Context errors: {:?}", errs);
+            return Err(Error::read_od_desc(pos, errs));
         }
         let i = item as usize;
         let idx = ec::Idx::from(od_list.indexes()[i]);
@@ -586,8 +1086,9 @@ impl Master {
         let res = self.ctx.read_oe(item, od_list, &mut oe_list);
         let pos = ec::SdoPos::from(item);
         if res <= 0 {
-            log::debug!("Context errors: {:?}", self.ctx_errors());
-            return Err(Error::ReadOeList(pos));
+            let errs = self.ctx_errors();
+            log::debug!("Context errors: {:?}", errs);
+            return Err(Error::read_oe_list(pos, errs));
         }
         Ok(oe_list)
     }
@@ -597,8 +1098,9 @@ impl Master {
         let res = self.ctx.read_od_list(u16::from(slave) + 1, &mut od_list);
 
         if res <= 0 {
-            log::debug!("Context errors: {:?}", self.ctx_errors());
-            return Err(Error::ReadOdList(slave));
+            let errs = self.ctx_errors();
+            log::debug!("Context errors: {:?}", errs);
+            return Err(Error::read_od_list(slave, errs));
         }
         log::debug!(
             "CoE Object Description: found {} entries",
@@ -662,12 +1164,12 @@ impl Master {
         if wkc <= 0 {
             let errs = self.ctx_errors();
             log::debug!("Context errors: {:?}", errs);
-            for e in errs {
+            for e in &errs {
                 if e.err_type == ctx::ErrType::Packet && e.abort_code == 3 {
                     log::warn!("data container too small for type");
                 }
             }
-            return Err(Error::ReadSdo(slave, idx));
+            return Err(Error::read_sdo(slave, idx, errs));
         }
         Ok(slice)
     }
@@ -780,7 +1282,7 @@ impl Master {
         if wkc <= 0 {
             let errs = self.ctx_errors();
             log::debug!("Context errors: {:?}", errs);
-            return Err(Error::WriteSdo(slave, idx));
+            return Err(Error::write_sdo(slave, idx, errs));
         }
         Ok(())
     }
@@ -809,7 +1311,7 @@ impl Master {
         if wkc <= 0 {
             let errs = self.ctx_errors();
             log::debug!("Context errors: {:?}", errs);
-            return Err(Error::WriteSdo(slave, idx));
+            return Err(Error::write_sdo(slave, idx, errs));
         }
         Ok(())
     }
@@ -818,53 +1320,16 @@ impl Master {
     pub fn pdo_values(&self) -> Vec<Vec<(ec::Idx, Vec<ec::Value>)>> {
         let mut all_pdos = vec![];
         for (i, slave) in self.slaves().iter().enumerate() {
-            let mut slave_pdos = vec![];
-            if let Some(pdo_meta_data) = self.pdos.get(i) {
-                let inputs: &[u8] = slave.inputs();
-                let outputs: &[u8] = slave.outputs();
-
-                for (pdo_info, pdo_entries) in pdo_meta_data {
-                    let mut pdos = vec![];
-                    for PdoEntryInfo {
-                        bit_len,
-                        offset,
-                        data_type,
-                        sm,
-                        ..
-                    } in pdo_entries
-                    {
-                        let ec::Offset { byte, bit } = *offset;
-                        let slice = match sm {
-                            ec::SmType::Inputs => Some(inputs),
-                            ec::SmType::Outputs => Some(outputs),
-                            _ => None,
-                        };
-                        match slice {
-                            Some(d) => {
-                                let len = byte_cnt(*bit_len as usize);
-                                let raw = &d[byte..byte + len];
-                                match util::value_from_slice(*data_type, raw, bit as usize) {
-                                    Ok(val) => {
-                                        pdos.push(val);
-                                    }
-                                    Err(err) => {
-                                        log::warn!("{}", err);
-                                    }
-                                }
-                            }
-                            None => {
-                                log::warn!("Unexpected SM type: {:?}", sm);
-                            }
-                        }
-                    }
-                    slave_pdos.push((pdo_info.idx, pdos));
+            match self.pdos.get(i) {
+                Some(pdos) => {
+                    all_pdos.push(pdo::pdo_values(pdos, slave.inputs(), slave.outputs()));
+                }
+                None => {
+                    log::warn!("Could not find PDO meta data for Slave {}", i);
+                    all_pdos.push(vec![]);
                 }
-            } else {
-                log::warn!("Could not find PDO meta data for Slave {}", i);
             }
-            all_pdos.push(slave_pdos);
         }
-
         all_pdos
     }
 
@@ -874,45 +1339,24 @@ impl Master {
         idx: ec::PdoEntryIdx,
         v: ec::Value,
     ) -> Result<()> {
-        let (data_type, offset) = {
-            let e = self
-                .pdos
-                .get(usize::from(slave))
-                .ok_or(Error::SlaveNotFound(slave))?
-                .iter()
-                .find(|(info, _)| info.idx == idx.idx)
-                .and_then(|(_, entries)| entries.iter().find(|e| e.idx == idx))
-                .ok_or(Error::PdoEntryNotFound(idx))?;
-            if e.sm != ec::SmType::Outputs {
-                return Err(Error::InvalidSmType);
-            }
-            (e.data_type, e.offset)
-        };
-        let s: &mut ctx::Slave = self
+        let pdos = self
+            .pdos
+            .get(usize::from(slave))
+            .ok_or(Error::SlaveNotFound(slave))?;
+        let s = self
+            .ctx
             .slaves_mut()
-            .get_mut(usize::from(slave))
+            .get_mut(usize::from(slave) + 1)
             .ok_or(Error::SlaveNotFound(slave))?;
-        let bytes = util::value_to_bytes(v)?;
-
-        if data_type == ec::DataType::Bool {
-            debug_assert_eq!(bytes.len(), 1);
-            let mask = 1 << offset.bit;
-            if bytes[0] == 1 {
-                s.outputs_mut()[offset.byte] |= mask; // Set Bit
-            } else {
-                s.outputs_mut()[offset.byte] &= !mask; // Clear Bit
-            }
-        } else {
-            for (i, b) in bytes.into_iter().enumerate() {
-                s.outputs_mut()[offset.byte + i] = b;
-            }
-        }
-        Ok(())
+        pdo::set_pdo_value(pdos, s.outputs_mut(), idx, v)
     }
 
     fn ctx_errors(&mut self) -> Vec<ctx::Error> {
         let mut errors = vec![];
         while let Some(e) = self.ctx.pop_error() {
+            if e.err_type == ctx::ErrType::Emergency {
+                self.record_emergency(&e);
+            }
             errors.push(e);
         }
         errors
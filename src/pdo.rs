@@ -0,0 +1,323 @@
+use crate::{byte_cnt, ec, util, Error, PdoEntryInfo, PdoInfo, Result, SlavePdoConfig};
+
+/// Description of a PDO (e.g. from an ESI file or the SII)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdoDescription {
+    pub idx: ec::Idx,
+    pub name: String,
+    /// [`ec::SmType::Outputs`] for RxPDOs and [`ec::SmType::Inputs`] for TxPDOs
+    pub sm_type: ec::SmType,
+    /// The SM the PDO is assigned to by default.
+    pub sm: Option<ec::SmIdx>,
+    /// The mapping can't be changed.
+    pub fixed: bool,
+    pub entries: Vec<PdoEntryDescription>,
+}
+
+/// Description of a default PDO entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdoEntryDescription {
+    pub sdo: ec::SdoIdx,
+    pub bit_len: u8,
+    pub name: String,
+    pub data_type: ec::DataType,
+}
+
+/// Mapping of the PDOs that are assigned by default.
+///
+/// Like the mapping that is read via CoE the RxPDOs come first
+/// and the offsets are relative to the start of each SM.
+pub(crate) fn default_pdo_info(pdos: &[PdoDescription]) -> PdoInfo {
+    let mut res = vec![];
+    let mut pdo_pos = 0;
+    let mut entry_pos = 0;
+    for t in &[ec::SmType::Outputs, ec::SmType::Inputs] {
+        let mut sms: Vec<u8> = pdos
+            .iter()
+            .filter(|pdo| pdo.sm_type == *t)
+            .filter_map(|pdo| pdo.sm.map(u8::from))
+            .collect();
+        sms.sort_unstable();
+        sms.dedup();
+        for sm in sms {
+            let sm = ec::SmIdx::new(sm);
+            let mut bit_offset = 0_usize;
+            let assigned = pdos
+                .iter()
+                .filter(|pdo| pdo.sm == Some(sm) && !pdo.entries.is_empty());
+            for pdo in assigned {
+                let mut entries = vec![];
+                for (i, e) in pdo.entries.iter().enumerate() {
+                    entries.push(PdoEntryInfo {
+                        idx: ec::PdoEntryIdx::new(u16::from(pdo.idx), i as u8 + 1),
+                        pos: ec::PdoEntryPos::new(entry_pos),
+                        data_type: e.data_type,
+                        offset: ec::Offset {
+                            byte: bit_offset / 8,
+                            bit: (bit_offset % 8) as u32,
+                        },
+                        bit_len: e.bit_len as usize,
+                        name: e.name.clone(),
+                        sm: *t,
+                        sdo: e.sdo,
+                    });
+                    bit_offset += e.bit_len as usize;
+                    entry_pos += 1;
+                }
+                let info = ec::PdoInfo {
+                    sm,
+                    pos: ec::PdoPos::new(pdo_pos),
+                    idx: pdo.idx,
+                    entry_count: entries.len() as u8,
+                    name: pdo.name.clone(),
+                };
+                res.push((info, entries));
+                pdo_pos += 1;
+            }
+        }
+    }
+    res
+}
+
+/// Descriptions of the PDOs that are assigned by a PDO configuration.
+///
+/// Names and data types are taken from the `known` PDOs
+/// (e.g. of an ESI file) if they describe the same PDO or object.
+pub(crate) fn configured_pdos(
+    cfg: &SlavePdoConfig,
+    known: &[PdoDescription],
+) -> Vec<PdoDescription> {
+    let known_entry = |sdo: ec::SdoIdx| {
+        known
+            .iter()
+            .flat_map(|pdo| pdo.entries.iter())
+            .find(|e| e.sdo == sdo)
+    };
+    let mut res = vec![];
+    for sm in &cfg.sms {
+        for pdo in &sm.pdos {
+            let desc = known.iter().find(|d| d.idx == pdo.idx);
+            let entries = match &pdo.entries {
+                Some(entries) => entries
+                    .iter()
+                    .map(|e| PdoEntryDescription {
+                        sdo: e.sdo,
+                        bit_len: e.bit_len,
+                        name: known_entry(e.sdo)
+                            .map(|k| k.name.clone())
+                            .unwrap_or_default(),
+                        data_type: known_entry(e.sdo).map_or(ec::DataType::Raw, |k| k.data_type),
+                    })
+                    .collect(),
+                None => desc.map(|d| d.entries.clone()).unwrap_or_default(),
+            };
+            // RxPDOs use the indexes 0x1600-0x17FF.
+            let sm_type = match desc {
+                Some(d) => d.sm_type,
+                None if (0x1600..0x1800).contains(&u16::from(pdo.idx)) => ec::SmType::Outputs,
+                None => ec::SmType::Inputs,
+            };
+            res.push(PdoDescription {
+                idx: pdo.idx,
+                name: desc.map(|d| d.name.clone()).unwrap_or_default(),
+                sm_type,
+                sm: Some(sm.sm),
+                fixed: matches!(desc, Some(d) if d.fixed),
+                entries,
+            });
+        }
+    }
+    res
+}
+
+/// Read the values of the PDOs of a slave from its process data.
+pub(crate) fn pdo_values(
+    pdos: &PdoInfo,
+    inputs: &[u8],
+    outputs: &[u8],
+) -> Vec<(ec::Idx, Vec<ec::Value>)> {
+    let mut slave_pdos = vec![];
+    for (pdo_info, pdo_entries) in pdos {
+        let mut values = vec![];
+        for PdoEntryInfo {
+            bit_len,
+            offset,
+            data_type,
+            sm,
+            ..
+        } in pdo_entries
+        {
+            let ec::Offset { byte, bit } = *offset;
+            let slice = match sm {
+                ec::SmType::Inputs => Some(inputs),
+                ec::SmType::Outputs => Some(outputs),
+                _ => None,
+            };
+            match slice {
+                Some(d) => {
+                    let len = byte_cnt(*bit_len);
+                    let raw = match d.get(byte..byte + len) {
+                        Some(raw) => raw,
+                        None => {
+                            log::warn!("PDO entry at byte {} is out of the process data", byte);
+                            continue;
+                        }
+                    };
+                    match util::value_from_slice(*data_type, raw, bit as usize) {
+                        Ok(val) => {
+                            values.push(val);
+                        }
+                        Err(err) => {
+                            log::warn!("{}", err);
+                        }
+                    }
+                }
+                None => {
+                    log::warn!("Unexpected SM type: {:?}", sm);
+                }
+            }
+        }
+        slave_pdos.push((pdo_info.idx, values));
+    }
+    slave_pdos
+}
+
+/// Write the value of a PDO entry to the outputs of a slave.
+pub(crate) fn set_pdo_value(
+    pdos: &PdoInfo,
+    outputs: &mut [u8],
+    idx: ec::PdoEntryIdx,
+    v: ec::Value,
+) -> Result<()> {
+    let e = pdos
+        .iter()
+        .find(|(info, _)| info.idx == idx.idx)
+        .and_then(|(_, entries)| entries.iter().find(|e| e.idx == idx))
+        .ok_or(Error::PdoEntryNotFound(idx))?;
+    if e.sm != ec::SmType::Outputs {
+        return Err(Error::InvalidSmType);
+    }
+    let (data_type, offset) = (e.data_type, e.offset);
+    let bytes = util::value_to_bytes(v)?;
+
+    let target = outputs
+        .get_mut(offset.byte..offset.byte + bytes.len())
+        .ok_or(Error::PdoEntryOutOfRange(idx))?;
+
+    if data_type == ec::DataType::Bool {
+        debug_assert_eq!(bytes.len(), 1);
+        let mask = 1 << offset.bit;
+        if bytes[0] == 1 {
+            target[0] |= mask; // Set Bit
+        } else {
+            target[0] &= !mask; // Clear Bit
+        }
+    } else {
+        target.copy_from_slice(&bytes);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pdo(idx: u16, sm_type: ec::SmType, sm: Option<u8>, bit_lens: &[u8]) -> PdoDescription {
+        PdoDescription {
+            idx: ec::Idx::new(idx),
+            name: format!("PDO 0x{:X}", idx),
+            sm_type,
+            sm: sm.map(ec::SmIdx::new),
+            fixed: true,
+            entries: bit_lens
+                .iter()
+                .enumerate()
+                .map(|(i, bit_len)| PdoEntryDescription {
+                    sdo: ec::SdoIdx::new(0x6000, i as u8 + 1),
+                    bit_len: *bit_len,
+                    name: format!("Entry {}", i),
+                    data_type: ec::DataType::Bool,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn default_pdo_mapping() {
+        let pdos = vec![
+            pdo(0x1A00, ec::SmType::Inputs, Some(3), &[1, 7]),
+            pdo(0x1A01, ec::SmType::Inputs, None, &[8]),
+            pdo(0x1600, ec::SmType::Outputs, Some(2), &[1, 1]),
+            pdo(0x1601, ec::SmType::Outputs, Some(2), &[16]),
+        ];
+        let info = default_pdo_info(&pdos);
+        let idx: Vec<_> = info.iter().map(|(pdo, _)| u16::from(pdo.idx)).collect();
+        assert_eq!(idx, vec![0x1600, 0x1601, 0x1A00]);
+
+        let (pdo, entries) = &info[1];
+        assert_eq!(pdo.pos, ec::PdoPos::new(1));
+        assert_eq!(pdo.sm, ec::SmIdx::new(2));
+        assert_eq!(entries[0].pos, ec::PdoEntryPos::new(2));
+        assert_eq!(entries[0].offset, ec::Offset { byte: 0, bit: 2 });
+        assert_eq!(entries[0].sm, ec::SmType::Outputs);
+
+        let (pdo, entries) = &info[2];
+        assert_eq!(pdo.entry_count, 2);
+        assert_eq!(entries[1].idx, ec::PdoEntryIdx::new(0x1A00, 2));
+        assert_eq!(entries[1].offset, ec::Offset { byte: 0, bit: 1 });
+        assert_eq!(entries[1].sm, ec::SmType::Inputs);
+    }
+
+    #[test]
+    fn describe_configured_pdos() {
+        let known = vec![
+            pdo(0x1A00, ec::SmType::Inputs, Some(3), &[1, 7]),
+            pdo(0x1A01, ec::SmType::Inputs, None, &[8]),
+        ];
+        let cfg = SlavePdoConfig {
+            sms: vec![crate::SmPdoConfig {
+                sm: ec::SmIdx::new(3),
+                fixed: false,
+                pdos: vec![
+                    crate::PdoConfig {
+                        idx: ec::Idx::new(0x1A01),
+                        entries: None,
+                    },
+                    crate::PdoConfig {
+                        idx: ec::Idx::new(0x1A02),
+                        entries: Some(vec![crate::PdoEntryConfig {
+                            sdo: ec::SdoIdx::new(0x6000, 2),
+                            bit_len: 7,
+                        }]),
+                    },
+                ],
+            }],
+        };
+        let pdos = configured_pdos(&cfg, &known);
+        assert_eq!(pdos.len(), 2);
+        assert_eq!(pdos[0].sm, Some(ec::SmIdx::new(3)));
+        assert_eq!(pdos[0].entries, known[1].entries);
+        assert_eq!(pdos[1].sm_type, ec::SmType::Inputs);
+        assert_eq!(pdos[1].entries[0].name, "Entry 1");
+
+        let info = default_pdo_info(&pdos);
+        let idx: Vec<_> = info.iter().map(|(pdo, _)| u16::from(pdo.idx)).collect();
+        assert_eq!(idx, vec![0x1A01, 0x1A02]);
+    }
+
+    #[test]
+    fn set_pdo_values_inside_the_outputs() {
+        let pdos = vec![pdo(0x1600, ec::SmType::Outputs, Some(2), &[1, 1])];
+        let info = default_pdo_info(&pdos);
+        let idx = ec::PdoEntryIdx::new(0x1600, 2);
+
+        let mut outputs = [0];
+        set_pdo_value(&info, &mut outputs, idx, ec::Value::Bool(true)).unwrap();
+        assert_eq!(outputs, [0b10]);
+
+        assert!(matches!(
+            set_pdo_value(&info, &mut [], idx, ec::Value::Bool(true)),
+            Err(Error::PdoEntryOutOfRange(i)) if i == idx
+        ));
+    }
+}
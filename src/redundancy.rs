@@ -0,0 +1,142 @@
+use crate::{ec, Error, Master, Result, EC_NOFRAME};
+use std::time::Duration;
+
+/// ESC register: DL status
+const REG_DL_STATUS: u16 = 0x0110;
+
+/// DL status: physical link on port 1
+const DL_STATUS_LINK_PORT_1: u16 = 1 << 5;
+
+const DL_STATUS_TIMEOUT: Duration = Duration::from_micros(2_000);
+
+/// Network interface of a master in redundancy mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Port {
+    Primary,
+    Secondary,
+}
+
+/// Status of the ring in cable redundancy mode
+///
+/// SOEM doesn't tell on which interface the frames of a slave arrive,
+/// so the status is a heuristic that is derived from the DL status
+/// (register `0x0110`) of the slaves. It has some limits:
+///
+/// - It assumes a line topology where each slave is connected
+///   to its successor by port 1. Junctions or a different
+///   cabling lead to wrong results.
+/// - A break is only detected by the link state of port 1,
+///   so a cable that has a link but drops the frames is not detected.
+/// - All slaves behind the first open port are assigned to the secondary
+///   interface, even if the ring is broken at more than one point.
+///   Slaves that don't respond at all have no port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedundancyStatus {
+    line_break: bool,
+    ports: Vec<Option<Port>>,
+}
+
+impl RedundancyStatus {
+    fn from_dl_status(dl_status: &[Option<u16>]) -> Self {
+        let mut line_break = false;
+        let mut port = Port::Primary;
+        let ports = dl_status
+            .iter()
+            .map(|status| match status {
+                Some(status) => {
+                    let current = port;
+                    if status & DL_STATUS_LINK_PORT_1 == 0 {
+                        line_break = true;
+                        port = Port::Secondary;
+                    }
+                    Some(current)
+                }
+                None => {
+                    line_break = true;
+                    port = Port::Secondary;
+                    None
+                }
+            })
+            .collect();
+        Self { line_break, ports }
+    }
+
+    /// The ring is broken at (at least) one point.
+    #[must_use]
+    pub const fn line_break(&self) -> bool {
+        self.line_break
+    }
+
+    /// The port that presumably carries the frames of a slave
+    /// or `None` if the slave is not reachable at all.
+    #[must_use]
+    pub fn port(&self, slave: ec::SlavePos) -> Option<Port> {
+        self.ports.get(usize::from(slave)).copied().flatten()
+    }
+}
+
+impl Master {
+    #[must_use]
+    pub fn is_redundant(&self) -> bool {
+        self.ctx.is_redundant()
+    }
+
+    /// Read the link states of all slaves to detect a line break.
+    pub fn redundancy_status(&mut self) -> Result<RedundancyStatus> {
+        if !self.ctx.is_redundant() {
+            return Err(Error::NotRedundant);
+        }
+        let mut dl_status = Vec::with_capacity(self.ctx.slave_count());
+        for i in 1..=self.ctx.slave_count() {
+            let addr = self.ctx.slaves()[i].config_addr();
+            let mut data = [0; 2];
+            let wkc = self
+                .ctx
+                .fprd(addr, REG_DL_STATUS, &mut data, DL_STATUS_TIMEOUT);
+            if wkc == EC_NOFRAME {
+                return Err(Error::NoFrame);
+            }
+            dl_status.push(Some(u16::from_le_bytes(data)).filter(|_| wkc > 0));
+        }
+        let status = RedundancyStatus::from_dl_status(&dl_status);
+        if status.line_break() {
+            log::warn!("Line break detected: {:?}", status);
+        }
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINKED: u16 = 0x0270 | DL_STATUS_LINK_PORT_1;
+    const OPEN: u16 = 0x0250;
+
+    #[test]
+    fn redundancy_status_from_dl_status() {
+        let pos = ec::SlavePos::new;
+
+        let status = RedundancyStatus::from_dl_status(&[Some(LINKED), Some(LINKED)]);
+        assert!(!status.line_break());
+        assert_eq!(status.port(pos(0)), Some(Port::Primary));
+        assert_eq!(status.port(pos(1)), Some(Port::Primary));
+        assert_eq!(status.port(pos(2)), None);
+
+        let status = RedundancyStatus::from_dl_status(&[Some(LINKED), Some(OPEN), Some(LINKED)]);
+        assert!(status.line_break());
+        assert_eq!(status.port(pos(0)), Some(Port::Primary));
+        assert_eq!(status.port(pos(1)), Some(Port::Primary));
+        assert_eq!(status.port(pos(2)), Some(Port::Secondary));
+
+        let status = RedundancyStatus::from_dl_status(&[Some(LINKED), None, Some(LINKED)]);
+        assert!(status.line_break());
+        assert_eq!(status.port(pos(1)), None);
+        assert_eq!(status.port(pos(2)), Some(Port::Secondary));
+
+        // cable to the secondary interface is cut
+        let status = RedundancyStatus::from_dl_status(&[Some(LINKED), Some(OPEN)]);
+        assert!(status.line_break());
+        assert_eq!(status.port(pos(1)), Some(Port::Primary));
+    }
+}
@@ -0,0 +1,188 @@
+use thiserror::Error;
+
+/// CoE SDO abort code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SdoAbortCode {
+    #[error("Toggle bit not changed")]
+    ToggleBit,
+    #[error("SDO protocol timeout")]
+    Timeout,
+    #[error("Client/Server command specifier not valid or unknown")]
+    InvalidCommand,
+    #[error("Invalid block size (block mode only)")]
+    InvalidBlockSize,
+    #[error("Invalid sequence number (block mode only)")]
+    InvalidSequenceNumber,
+    #[error("CRC error (block mode only)")]
+    CrcError,
+    #[error("Out of memory")]
+    OutOfMemory,
+    #[error("Unsupported access to an object")]
+    UnsupportedAccess,
+    #[error("Attempt to read a write-only object")]
+    WriteOnly,
+    #[error("Attempt to write a read-only object")]
+    ReadOnly,
+    #[error("Subindex cannot be written, subindex 0 must be 0 for write access")]
+    SubIdxNotWritable,
+    #[error("Complete access is not supported for objects of variable length")]
+    CompleteAccessNotSupported,
+    #[error("Object length exceeds the mailbox size")]
+    ObjectTooLong,
+    #[error("Object mapped to RxPDO, SDO download blocked")]
+    MappedToRxPdo,
+    #[error("The object does not exist in the object dictionary")]
+    ObjectNotFound,
+    #[error("The object cannot be mapped into the PDO")]
+    NotMappable,
+    #[error("The number and length of the mapped objects would exceed the PDO length")]
+    PdoLengthExceeded,
+    #[error("General parameter incompatibility")]
+    ParameterIncompatibility,
+    #[error("General internal incompatibility in the device")]
+    InternalIncompatibility,
+    #[error("Access failed due to a hardware error")]
+    HardwareError,
+    #[error("Data type does not match, length of service parameter does not match")]
+    LengthMismatch,
+    #[error("Data type does not match, length of service parameter too high")]
+    LengthTooHigh,
+    #[error("Data type does not match, length of service parameter too low")]
+    LengthTooLow,
+    #[error("Subindex does not exist")]
+    SubIdxNotFound,
+    #[error("Value range of parameter exceeded")]
+    ValueRangeExceeded,
+    #[error("Value of parameter written too high")]
+    ValueTooHigh,
+    #[error("Value of parameter written too low")]
+    ValueTooLow,
+    #[error("Maximum value is less than minimum value")]
+    MaxLessThanMin,
+    #[error("General error")]
+    GeneralError,
+    #[error("Data cannot be transferred or stored to the application")]
+    TransferFailed,
+    #[error("Data cannot be transferred or stored to the application because of local control")]
+    LocalControl,
+    #[error("Data cannot be transferred or stored to the application because of the present device state")]
+    DeviceState,
+    #[error("Object dictionary dynamic generation fails or no object dictionary is present")]
+    NoObjectDictionary,
+    #[error("Unknown abort code 0x{0:08X}")]
+    Unknown(u32),
+}
+
+impl From<u32> for SdoAbortCode {
+    fn from(code: u32) -> Self {
+        match code {
+            0x0503_0000 => Self::ToggleBit,
+            0x0504_0000 => Self::Timeout,
+            0x0504_0001 => Self::InvalidCommand,
+            0x0504_0002 => Self::InvalidBlockSize,
+            0x0504_0003 => Self::InvalidSequenceNumber,
+            0x0504_0004 => Self::CrcError,
+            0x0504_0005 => Self::OutOfMemory,
+            0x0601_0000 => Self::UnsupportedAccess,
+            0x0601_0001 => Self::WriteOnly,
+            0x0601_0002 => Self::ReadOnly,
+            0x0601_0003 => Self::SubIdxNotWritable,
+            0x0601_0004 => Self::CompleteAccessNotSupported,
+            0x0601_0005 => Self::ObjectTooLong,
+            0x0601_0006 => Self::MappedToRxPdo,
+            0x0602_0000 => Self::ObjectNotFound,
+            0x0604_0041 => Self::NotMappable,
+            0x0604_0042 => Self::PdoLengthExceeded,
+            0x0604_0043 => Self::ParameterIncompatibility,
+            0x0604_0047 => Self::InternalIncompatibility,
+            0x0606_0000 => Self::HardwareError,
+            0x0607_0010 => Self::LengthMismatch,
+            0x0607_0012 => Self::LengthTooHigh,
+            0x0607_0013 => Self::LengthTooLow,
+            0x0609_0011 => Self::SubIdxNotFound,
+            0x0609_0030 => Self::ValueRangeExceeded,
+            0x0609_0031 => Self::ValueTooHigh,
+            0x0609_0032 => Self::ValueTooLow,
+            0x0609_0036 => Self::MaxLessThanMin,
+            0x0800_0000 => Self::GeneralError,
+            0x0800_0020 => Self::TransferFailed,
+            0x0800_0021 => Self::LocalControl,
+            0x0800_0022 => Self::DeviceState,
+            0x0800_0023 => Self::NoObjectDictionary,
+            _ => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<SdoAbortCode> for u32 {
+    fn from(code: SdoAbortCode) -> Self {
+        match code {
+            SdoAbortCode::ToggleBit => 0x0503_0000,
+            SdoAbortCode::Timeout => 0x0504_0000,
+            SdoAbortCode::InvalidCommand => 0x0504_0001,
+            SdoAbortCode::InvalidBlockSize => 0x0504_0002,
+            SdoAbortCode::InvalidSequenceNumber => 0x0504_0003,
+            SdoAbortCode::CrcError => 0x0504_0004,
+            SdoAbortCode::OutOfMemory => 0x0504_0005,
+            SdoAbortCode::UnsupportedAccess => 0x0601_0000,
+            SdoAbortCode::WriteOnly => 0x0601_0001,
+            SdoAbortCode::ReadOnly => 0x0601_0002,
+            SdoAbortCode::SubIdxNotWritable => 0x0601_0003,
+            SdoAbortCode::CompleteAccessNotSupported => 0x0601_0004,
+            SdoAbortCode::ObjectTooLong => 0x0601_0005,
+            SdoAbortCode::MappedToRxPdo => 0x0601_0006,
+            SdoAbortCode::ObjectNotFound => 0x0602_0000,
+            SdoAbortCode::NotMappable => 0x0604_0041,
+            SdoAbortCode::PdoLengthExceeded => 0x0604_0042,
+            SdoAbortCode::ParameterIncompatibility => 0x0604_0043,
+            SdoAbortCode::InternalIncompatibility => 0x0604_0047,
+            SdoAbortCode::HardwareError => 0x0606_0000,
+            SdoAbortCode::LengthMismatch => 0x0607_0010,
+            SdoAbortCode::LengthTooHigh => 0x0607_0012,
+            SdoAbortCode::LengthTooLow => 0x0607_0013,
+            SdoAbortCode::SubIdxNotFound => 0x0609_0011,
+            SdoAbortCode::ValueRangeExceeded => 0x0609_0030,
+            SdoAbortCode::ValueTooHigh => 0x0609_0031,
+            SdoAbortCode::ValueTooLow => 0x0609_0032,
+            SdoAbortCode::MaxLessThanMin => 0x0609_0036,
+            SdoAbortCode::GeneralError => 0x0800_0000,
+            SdoAbortCode::TransferFailed => 0x0800_0020,
+            SdoAbortCode::LocalControl => 0x0800_0021,
+            SdoAbortCode::DeviceState => 0x0800_0022,
+            SdoAbortCode::NoObjectDictionary => 0x0800_0023,
+            SdoAbortCode::Unknown(code) => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_abort_codes() {
+        assert_eq!(
+            SdoAbortCode::from(0x0602_0000),
+            SdoAbortCode::ObjectNotFound
+        );
+        assert_eq!(SdoAbortCode::from(0x0601_0002), SdoAbortCode::ReadOnly);
+        assert_eq!(SdoAbortCode::from(0x0504_0004), SdoAbortCode::CrcError);
+        for code in 0x0504_0000..=0x0504_0005 {
+            assert_eq!(u32::from(SdoAbortCode::from(code)), code);
+            assert!(!matches!(
+                SdoAbortCode::from(code),
+                SdoAbortCode::Unknown(_)
+            ));
+        }
+        assert_eq!(u32::from(SdoAbortCode::SubIdxNotFound), 0x0609_0011);
+        assert_eq!(
+            SdoAbortCode::from(0x1234_5678),
+            SdoAbortCode::Unknown(0x1234_5678)
+        );
+        assert_eq!(u32::from(SdoAbortCode::Unknown(0x1234_5678)), 0x1234_5678);
+        assert_eq!(
+            SdoAbortCode::ReadOnly.to_string(),
+            "Attempt to write a read-only object"
+        );
+    }
+}
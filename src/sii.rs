@@ -0,0 +1,613 @@
+use crate::{ec, Error, Master, PdoDescription, PdoEntryDescription, Result, MAX_SM_CNT};
+use num_traits::cast::FromPrimitive;
+use std::{convert::TryFrom, time::Duration};
+
+pub(crate) const EEPROM_TIMEOUT: Duration = Duration::from_micros(20_000);
+
+/// EEPROM control/status register of the ESC
+const REG_EEPROM_STATUS: u16 = 0x0502;
+const EEPROM_STATUS_BUSY: u16 = 0x8000;
+/// Acknowledge/command error
+const EEPROM_STATUS_ERROR: u16 = 0x2000;
+/// Checksum and device info errors
+///
+/// They are latched when the ESC loads the EEPROM at power-up
+/// and don't indicate a failed read.
+const EEPROM_STATUS_LOAD_ERRORS: u16 = 0x1800;
+
+/// Word address of the EEPROM size (in KBit - 1)
+const SII_SIZE: u16 = 0x003E;
+
+/// The EEPROM address space (64K words)
+const SII_MAX_LEN: usize = 0x2_0000;
+
+/// Byte offset of the first category
+const SII_CATEGORIES: usize = 0x80;
+
+const CAT_STRINGS: u16 = 10;
+const CAT_GENERAL: u16 = 30;
+const CAT_FMMU: u16 = 40;
+const CAT_SYNCM: u16 = 41;
+const CAT_TXPDO: u16 = 50;
+const CAT_RXPDO: u16 = 51;
+const CAT_DC: u16 = 60;
+const CAT_END: u16 = 0xFFFF;
+
+const PDO_FIXED_CONTENT: u16 = 0x0010;
+
+/// Decoded slave information interface (SII)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sii {
+    pub alias: u16,
+    pub vendor_id: u32,
+    pub product_code: u32,
+    pub revision: u32,
+    pub serial_number: u32,
+    /// Supported mailbox protocols (bit mask)
+    pub mailbox_protocols: u16,
+    pub strings: Vec<String>,
+    pub general: Option<SiiGeneral>,
+    pub fmmus: Vec<SiiFmmu>,
+    pub sms: Vec<SiiSm>,
+    /// TxPDOs and RxPDOs
+    pub pdos: Vec<PdoDescription>,
+    pub dc_sync_modes: Vec<SiiDcSyncMode>,
+}
+
+/// General category of the SII
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiiGeneral {
+    pub group: Option<String>,
+    pub image: Option<String>,
+    pub order: Option<String>,
+    pub name: Option<String>,
+    pub coe_details: u8,
+    pub foe_details: u8,
+    pub eoe_details: u8,
+    pub soe_channels: u8,
+    pub ds402_channels: u8,
+    pub flags: u8,
+    /// Current consumption from the E-Bus in mA
+    pub ebus_current: i16,
+}
+
+/// Usage of an FMMU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiiFmmu {
+    Unused,
+    Outputs,
+    Inputs,
+    SmStatus,
+    Unknown(u8),
+}
+
+/// Default settings of a sync manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiiSm {
+    pub start_addr: u16,
+    pub len: u16,
+    pub control: u8,
+    pub enable: u8,
+    /// `None` if the type is unknown.
+    pub sm_type: Option<ec::SmType>,
+}
+
+/// DC sync mode of the SII
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiiDcSyncMode {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Cycle time of SYNC0 in ns
+    pub cycle_time0: u32,
+    /// Shift of SYNC0 in ns
+    pub shift_time0: u32,
+    /// Shift of SYNC1 in ns
+    pub shift_time1: u32,
+    pub sync0_cycle_factor: i16,
+    pub sync1_cycle_factor: i16,
+    /// Value of the DC activation register (0x0980)
+    pub assign_activate: u16,
+}
+
+impl Sii {
+    /// Decode the header and the categories of raw SII data.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut header = Reader::new(data);
+        header.skip(0x08)?;
+        let alias = header.u16()?;
+        header.skip(0x06)?;
+        let vendor_id = header.u32()?;
+        let product_code = header.u32()?;
+        let revision = header.u32()?;
+        let serial_number = header.u32()?;
+        header.skip(0x18)?;
+        let mailbox_protocols = header.u16()?;
+
+        let categories = categories(data)?;
+        let strings = match categories.iter().find(|(t, _)| *t == CAT_STRINGS) {
+            Some((_, data)) => parse_strings(data)?,
+            None => vec![],
+        };
+        let mut sii = Self {
+            alias,
+            vendor_id,
+            product_code,
+            revision,
+            serial_number,
+            mailbox_protocols,
+            ..Self::default()
+        };
+        for (cat, data) in categories {
+            match cat {
+                CAT_GENERAL => sii.general = Some(parse_general(data, &strings)?),
+                CAT_FMMU => sii.fmmus = data.iter().map(|x| SiiFmmu::from(*x)).collect(),
+                CAT_SYNCM => sii.sms = parse_sms(data)?,
+                CAT_TXPDO => sii
+                    .pdos
+                    .extend(parse_pdos(data, ec::SmType::Inputs, &strings)?),
+                CAT_RXPDO => sii
+                    .pdos
+                    .extend(parse_pdos(data, ec::SmType::Outputs, &strings)?),
+                CAT_DC => sii.dc_sync_modes = parse_dc(data, &strings)?,
+                _ => {}
+            }
+        }
+        sii.strings = strings;
+        Ok(sii)
+    }
+}
+
+impl From<u8> for SiiFmmu {
+    fn from(x: u8) -> Self {
+        match x {
+            0 => Self::Unused,
+            1 => Self::Outputs,
+            2 => Self::Inputs,
+            3 => Self::SmStatus,
+            _ => Self::Unknown(x),
+        }
+    }
+}
+
+impl Master {
+    /// Read the slave information interface (SII) of a slave.
+    ///
+    /// The whole EEPROM is read, its size is taken from the SII header.
+    pub fn read_sii(&mut self, slave: ec::SlavePos) -> Result<Vec<u8>> {
+        let (pos, pdi) = self.sii_slave(slave)?;
+        let res = self.read_eeprom(slave, None);
+        self.restore_eeprom_control(pos, pdi);
+        res
+    }
+
+    /// Write the slave information interface (SII) of a slave.
+    ///
+    /// The data is written word by word starting at address `0`
+    /// and verified by reading it back.
+    /// The slave list still contains the values of the old SII
+    /// until the slaves are initialised again.
+    pub fn write_sii(&mut self, slave: ec::SlavePos, data: &[u8]) -> Result<()> {
+        if data.len() % 2 != 0 || data.len() > SII_MAX_LEN {
+            return Err(Error::SiiLength(data.len()));
+        }
+        let (pos, pdi) = self.sii_slave(slave)?;
+        let res = self.write_eeprom(slave, data);
+        self.restore_eeprom_control(pos, pdi);
+        // SOEM must not use the SII it cached before.
+        self.ctx.clear_sii_cache();
+        res
+    }
+
+    /// Read and decode the SII of a slave.
+    pub fn read_sii_info(&mut self, slave: ec::SlavePos) -> Result<Sii> {
+        let data = self.read_sii(slave)?;
+        Sii::parse(&data)
+    }
+
+    fn write_eeprom(&mut self, slave: ec::SlavePos, data: &[u8]) -> Result<()> {
+        let pos = u16::from(slave) + 1;
+        log::debug!("Write {} bytes to the SII of {:?}", data.len(), slave);
+        for (addr, word) in data.chunks(2).enumerate() {
+            let word = u16::from_le_bytes([word[0], word[1]]);
+            if self
+                .ctx
+                .write_eeprom(pos, addr as u16, word, EEPROM_TIMEOUT)
+                <= 0
+            {
+                log::debug!("Context errors: {:?}", self.ctx_errors());
+                return Err(Error::WriteSii(slave, addr as u16));
+            }
+        }
+        let written = self.read_eeprom(slave, Some(data.len()))?;
+        if let Some(i) = (0..data.len()).find(|i| written[*i] != data[*i]) {
+            log::warn!("SII of {:?} differs at byte {}", slave, i);
+            return Err(Error::VerifySii(slave, (i / 2) as u16));
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes or the whole EEPROM.
+    fn read_eeprom(&mut self, slave: ec::SlavePos, len: Option<usize>) -> Result<Vec<u8>> {
+        let pos = u16::from(slave) + 1;
+        if self.ctx.eeprom_to_master(pos) <= 0 {
+            return Err(Error::ReadSii(slave));
+        }
+        let s = &self.slaves()[usize::from(slave)];
+        let config_addr = s.config_addr();
+        let chunk = if s.eep_8byte() { 8 } else { 4 };
+        let len = match len {
+            Some(len) => len,
+            None => {
+                let size = self.read_eeprom_chunk(slave, config_addr, SII_SIZE)? as u16;
+                sii_len(size)
+            }
+        };
+        let mut data = Vec::with_capacity(len + chunk);
+        while data.len() < len {
+            let addr = (data.len() / 2) as u16;
+            let value = self.read_eeprom_chunk(slave, config_addr, addr)?;
+            data.extend_from_slice(&value.to_le_bytes()[..chunk]);
+        }
+        data.truncate(len);
+        Ok(data)
+    }
+
+    /// Read 4 or 8 bytes from the EEPROM.
+    ///
+    /// SOEM returns `0` if the read fails,
+    /// so the EEPROM status is checked afterwards.
+    fn read_eeprom_chunk(
+        &mut self,
+        slave: ec::SlavePos,
+        config_addr: u16,
+        addr: u16,
+    ) -> Result<u64> {
+        let value = self.ctx.read_eeprom_fp(config_addr, addr, EEPROM_TIMEOUT);
+        let mut status = [0; 2];
+        let wkc = self
+            .ctx
+            .fprd(config_addr, REG_EEPROM_STATUS, &mut status, EEPROM_TIMEOUT);
+        let status = u16::from_le_bytes(status);
+        if wkc <= 0 || status & (EEPROM_STATUS_BUSY | EEPROM_STATUS_ERROR) != 0 {
+            log::debug!(
+                "Could not read SII word 0x{:X} of {:?} (EEPROM status: 0x{:04X})",
+                addr,
+                slave,
+                status
+            );
+            return Err(Error::ReadSii(slave));
+        }
+        if status & EEPROM_STATUS_LOAD_ERRORS != 0 {
+            log::debug!(
+                "EEPROM of {:?} was not loaded correctly (EEPROM status: 0x{:04X})",
+                slave,
+                status
+            );
+        }
+        Ok(value)
+    }
+
+    /// The SOEM slave number and whether the PDI controls the EEPROM.
+    fn sii_slave(&self, slave: ec::SlavePos) -> Result<(u16, bool)> {
+        let s = self
+            .slaves()
+            .get(usize::from(slave))
+            .ok_or(Error::SlaveNotFound(slave))?;
+        Ok((u16::from(slave) + 1, s.eep_pdi()))
+    }
+
+    fn restore_eeprom_control(&mut self, pos: u16, pdi: bool) {
+        if pdi && self.ctx.eeprom_to_pdi(pos) <= 0 {
+            log::warn!("Could not hand the EEPROM of slave {} back to the PDI", pos);
+        }
+    }
+}
+
+/// Split the data into categories (type and data).
+fn categories(data: &[u8]) -> Result<Vec<(u16, &[u8])>> {
+    let mut categories = vec![];
+    let mut r = Reader::new(data);
+    r.skip(SII_CATEGORIES)?;
+    while r.remaining() >= 4 {
+        let cat = r.u16()?;
+        if cat == CAT_END {
+            break;
+        }
+        let len = r.u16()? as usize * 2;
+        categories.push((cat, r.bytes(len)?));
+    }
+    Ok(categories)
+}
+
+fn parse_strings(data: &[u8]) -> Result<Vec<String>> {
+    let mut r = Reader::new(data);
+    let cnt = r.u8()?;
+    (0..cnt)
+        .map(|_| {
+            let len = r.u8()? as usize;
+            Ok(String::from_utf8_lossy(r.bytes(len)?).into_owned())
+        })
+        .collect()
+}
+
+/// Strings are referenced by their index starting with `1`.
+fn string(strings: &[String], idx: u8) -> Option<String> {
+    (idx as usize)
+        .checked_sub(1)
+        .and_then(|i| strings.get(i))
+        .cloned()
+}
+
+fn parse_general(data: &[u8], strings: &[String]) -> Result<SiiGeneral> {
+    let mut r = Reader::new(data);
+    let group = string(strings, r.u8()?);
+    let image = string(strings, r.u8()?);
+    let order = string(strings, r.u8()?);
+    let name = string(strings, r.u8()?);
+    r.skip(1)?;
+    let coe_details = r.u8()?;
+    let foe_details = r.u8()?;
+    let eoe_details = r.u8()?;
+    let soe_channels = r.u8()?;
+    let ds402_channels = r.u8()?;
+    r.skip(1)?;
+    let flags = r.u8()?;
+    let ebus_current = r.i16()?;
+    Ok(SiiGeneral {
+        group,
+        image,
+        order,
+        name,
+        coe_details,
+        foe_details,
+        eoe_details,
+        soe_channels,
+        ds402_channels,
+        flags,
+        ebus_current,
+    })
+}
+
+fn parse_sms(data: &[u8]) -> Result<Vec<SiiSm>> {
+    let mut r = Reader::new(data);
+    let mut sms = vec![];
+    while r.remaining() >= 8 {
+        let start_addr = r.u16()?;
+        let len = r.u16()?;
+        let control = r.u8()?;
+        r.skip(1)?;
+        let enable = r.u8()?;
+        let sm_type = ec::SmType::try_from(r.u8()?).ok();
+        sms.push(SiiSm {
+            start_addr,
+            len,
+            control,
+            enable,
+            sm_type,
+        });
+    }
+    Ok(sms)
+}
+
+fn parse_pdos(data: &[u8], sm_type: ec::SmType, strings: &[String]) -> Result<Vec<PdoDescription>> {
+    let mut r = Reader::new(data);
+    let mut pdos = vec![];
+    while r.remaining() >= 8 {
+        let idx = r.u16()?;
+        let entry_cnt = r.u8()?;
+        let sm = r.u8()?;
+        r.skip(1)?; // DC sync
+        let name = string(strings, r.u8()?).unwrap_or_default();
+        let flags = r.u16()?;
+        let entries = (0..entry_cnt)
+            .map(|_| {
+                let idx = r.u16()?;
+                let sub_idx = r.u8()?;
+                let name = string(strings, r.u8()?).unwrap_or_default();
+                let data_type = ec::DataType::from_u8(r.u8()?).unwrap_or(ec::DataType::Raw);
+                let bit_len = r.u8()?;
+                r.skip(2)?; // flags
+                Ok(PdoEntryDescription {
+                    sdo: ec::SdoIdx::new(idx, sub_idx),
+                    bit_len,
+                    name,
+                    data_type,
+                })
+            })
+            .collect::<Result<_>>()?;
+        pdos.push(PdoDescription {
+            idx: ec::Idx::new(idx),
+            name,
+            sm_type,
+            sm: Some(sm).filter(|sm| *sm < MAX_SM_CNT).map(ec::SmIdx::new),
+            fixed: flags & PDO_FIXED_CONTENT != 0,
+            entries,
+        });
+    }
+    Ok(pdos)
+}
+
+fn parse_dc(data: &[u8], strings: &[String]) -> Result<Vec<SiiDcSyncMode>> {
+    let mut r = Reader::new(data);
+    let mut modes = vec![];
+    while r.remaining() >= 24 {
+        let cycle_time0 = r.u32()?;
+        let shift_time0 = r.u32()?;
+        let shift_time1 = r.u32()?;
+        let sync1_cycle_factor = r.i16()?;
+        let assign_activate = r.u16()?;
+        let sync0_cycle_factor = r.i16()?;
+        let name = string(strings, r.u8()?);
+        let description = string(strings, r.u8()?);
+        r.skip(4)?;
+        modes.push(SiiDcSyncMode {
+            name,
+            description,
+            cycle_time0,
+            shift_time0,
+            shift_time1,
+            sync0_cycle_factor,
+            sync1_cycle_factor,
+            assign_activate,
+        });
+    }
+    Ok(modes)
+}
+
+/// Reads little-endian values
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    const fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(Error::SiiTruncated(self.data.len()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// Number of bytes according to the size word of the SII.
+fn sii_len(size: u16) -> usize {
+    ((size as usize + 1) * 128).min(SII_MAX_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(cat: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = cat.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&((data.len() / 2) as u16).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn sii() -> Vec<u8> {
+        let mut data = vec![0; SII_CATEGORIES];
+        data[0x08] = 0x07; // alias
+        data[0x10..0x14].copy_from_slice(&2_u32.to_le_bytes());
+        data[0x14..0x18].copy_from_slice(&0x03f0_3052_u32.to_le_bytes());
+        data[0x18..0x1C].copy_from_slice(&0x0012_0000_u32.to_le_bytes());
+        data[0x38] = 0x04; // CoE
+        data.extend(category(CAT_STRINGS, b"\x03\x06EL1008\x05Input\x07Channel"));
+        data.extend(category(
+            CAT_GENERAL,
+            &[0, 0, 1, 1, 0, 0x23, 0, 0, 0, 0, 0, 0, 90, 0, 0, 0],
+        ));
+        data.extend(category(CAT_FMMU, &[2, 3]));
+        data.extend(category(CAT_SYNCM, &[0x00, 0x10, 1, 0, 0x20, 0, 0x01, 4]));
+        data.extend(category(
+            CAT_TXPDO,
+            &[
+                0x00, 0x1A, 1, 0, 0, 3, 0x10, 0, // PDO
+                0x00, 0x60, 1, 2, 1, 1, 0, 0, // entry
+            ],
+        ));
+        data.extend(category(
+            CAT_DC,
+            &[
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x03, 0, 0, 1, 0, 0, 0, 0, 0,
+            ],
+        ));
+        data.extend(&CAT_END.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn parse_sii() {
+        let sii = Sii::parse(&sii()).unwrap();
+        assert_eq!(sii.alias, 7);
+        assert_eq!(sii.vendor_id, 2);
+        assert_eq!(sii.product_code, 0x03f0_3052);
+        assert_eq!(sii.revision, 0x0012_0000);
+        assert_eq!(sii.mailbox_protocols, 0x04);
+        assert_eq!(sii.strings, vec!["EL1008", "Input", "Channel"]);
+
+        let general = sii.general.unwrap();
+        assert_eq!(general.name.as_deref(), Some("EL1008"));
+        assert_eq!(general.group, None);
+        assert_eq!(general.coe_details, 0x23);
+        assert_eq!(general.ebus_current, 90);
+
+        assert_eq!(sii.fmmus, vec![SiiFmmu::Inputs, SiiFmmu::SmStatus]);
+        assert_eq!(
+            sii.sms,
+            vec![SiiSm {
+                start_addr: 0x1000,
+                len: 1,
+                control: 0x20,
+                enable: 0x01,
+                sm_type: Some(ec::SmType::Inputs),
+            }]
+        );
+        assert_eq!(
+            sii.pdos,
+            vec![PdoDescription {
+                idx: ec::Idx::new(0x1A00),
+                name: "Channel".to_string(),
+                sm_type: ec::SmType::Inputs,
+                sm: Some(ec::SmIdx::new(0)),
+                fixed: true,
+                entries: vec![PdoEntryDescription {
+                    sdo: ec::SdoIdx::new(0x6000, 1),
+                    bit_len: 1,
+                    name: "Input".to_string(),
+                    data_type: ec::DataType::Bool,
+                }],
+            }]
+        );
+        assert_eq!(sii.dc_sync_modes.len(), 1);
+        assert_eq!(sii.dc_sync_modes[0].assign_activate, 0x0300);
+        assert_eq!(sii.dc_sync_modes[0].name.as_deref(), Some("EL1008"));
+    }
+
+    #[test]
+    fn truncated_sii() {
+        let mut data = sii();
+        data.truncate(SII_CATEGORIES + 10);
+        assert!(matches!(Sii::parse(&data), Err(Error::SiiTruncated(_))));
+        assert!(matches!(Sii::parse(&[0; 16]), Err(Error::SiiTruncated(16))));
+    }
+
+    #[test]
+    fn sii_len_from_size_word() {
+        assert_eq!(sii_len(0), 128);
+        assert_eq!(sii_len(0x0F), 2048);
+        assert_eq!(sii_len(0xFFFF), SII_MAX_LEN);
+    }
+}
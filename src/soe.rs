@@ -0,0 +1,407 @@
+//! Servo drive profile over EtherCAT (SoE)
+
+use crate::{ec, Error, Master, PdoEntryInfo, PdoInfo, Result};
+use std::{convert::TryFrom, ops::BitOr, time::Duration};
+
+const MBX_PROTO_SOE: u16 = 0x0010;
+
+const SOE_TIMEOUT: Duration = Duration::from_micros(700_000);
+
+/// Maximum number of drives of a slave
+const MAX_DRIVES: u8 = 8;
+
+/// Maximum number of IDNs in a telegram
+const MAX_MAPPING: usize = 64;
+
+/// Configuration list of the master data telegram (outputs)
+const IDN_MDT_CONFIG: u16 = 24;
+
+/// Configuration list of the amplifier telegram (inputs)
+const IDN_AT_CONFIG: u16 = 16;
+
+/// The control word is mapped in front of the MDT.
+const IDN_CONTROL_WORD: u16 = 134;
+
+/// The status word is mapped in front of the AT.
+const IDN_STATUS_WORD: u16 = 135;
+
+/// Telegram of the SoE process data
+struct Telegram {
+    /// IDN of the configuration list
+    config: u16,
+    /// IDN that is mapped in front of the list
+    word: u16,
+    sm_type: ec::SmType,
+    sm: u8,
+    name: &'static str,
+}
+
+const TELEGRAMS: [Telegram; 2] = [
+    Telegram {
+        config: IDN_MDT_CONFIG,
+        word: IDN_CONTROL_WORD,
+        sm_type: ec::SmType::Outputs,
+        sm: 2,
+        name: "MDT",
+    },
+    Telegram {
+        config: IDN_AT_CONFIG,
+        word: IDN_STATUS_WORD,
+        sm_type: ec::SmType::Inputs,
+        sm: 3,
+        name: "AT",
+    },
+];
+
+/// IDN that is mapped into a telegram
+struct MappedIdn {
+    idn: u16,
+    attribute: u32,
+    name: String,
+}
+
+/// Elements of an IDN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdnElements(u8);
+
+impl IdnElements {
+    pub const DATA_STATE: Self = Self(0x01);
+    pub const NAME: Self = Self(0x02);
+    pub const ATTRIBUTE: Self = Self(0x04);
+    pub const UNIT: Self = Self(0x08);
+    pub const MIN: Self = Self(0x10);
+    pub const MAX: Self = Self(0x20);
+    pub const VALUE: Self = Self(0x40);
+    pub const DEFAULT: Self = Self(0x80);
+}
+
+impl BitOr for IdnElements {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl From<IdnElements> for u8 {
+    fn from(elements: IdnElements) -> Self {
+        elements.0
+    }
+}
+
+impl Master {
+    /// Read elements of an IDN of a drive via SoE.
+    pub fn read_idn<'t>(
+        &mut self,
+        slave: ec::SlavePos,
+        drive_no: u8,
+        elements: IdnElements,
+        idn: u16,
+        target: &'t mut [u8],
+        timeout: Duration,
+    ) -> Result<&'t mut [u8]> {
+        let (wkc, slice) = self.ctx.soe_read(
+            u16::from(slave) + 1,
+            drive_no,
+            u8::from(elements),
+            idn,
+            target,
+            timeout,
+        );
+        if wkc <= 0 {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::ReadIdn(slave, drive_no, idn));
+        }
+        Ok(slice)
+    }
+
+    /// Write elements of an IDN of a drive via SoE.
+    pub fn write_idn(
+        &mut self,
+        slave: ec::SlavePos,
+        drive_no: u8,
+        elements: IdnElements,
+        idn: u16,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<()> {
+        let wkc = self.ctx.soe_write(
+            u16::from(slave) + 1,
+            drive_no,
+            u8::from(elements),
+            idn,
+            data,
+            timeout,
+        );
+        if wkc <= 0 {
+            log::debug!("Context errors: {:?}", self.ctx_errors());
+            return Err(Error::WriteIdn(slave, drive_no, idn));
+        }
+        Ok(())
+    }
+
+    /// Process data mapping of the drives of a SoE slave
+    /// according to their MDT and AT configuration lists.
+    ///
+    /// The drive number is part of the PDO index (see [`drive_pdo_idx`]).
+    pub(crate) fn soe_pdo_info(&mut self, slave: ec::SlavePos) -> Option<PdoInfo> {
+        let pos = u16::from(slave) + 1;
+        if self.slaves().get(usize::from(slave))?.mbx_proto() & MBX_PROTO_SOE == 0 {
+            return None;
+        }
+        let (wkc, output_bits, input_bits) = self.ctx.read_idn_map(pos);
+        if wkc <= 0 || output_bits + input_bits == 0 {
+            log::debug!("{:?}: no SoE process data found", slave);
+            return None;
+        }
+        let mut telegrams = vec![];
+        for drive_no in 0..MAX_DRIVES {
+            for (i, telegram) in TELEGRAMS.iter().enumerate() {
+                let mut idns = match self.read_idn_list(slave, drive_no, telegram.config) {
+                    Some(idns) if !idns.is_empty() => idns,
+                    _ => continue,
+                };
+                idns.insert(0, telegram.word);
+                let mut mapped = vec![];
+                for idn in idns {
+                    let attribute = if idn == telegram.word {
+                        IDN_ATTRIBUTE_U16
+                    } else {
+                        match self.idn_attribute(slave, drive_no, idn) {
+                            Some(attribute) => attribute,
+                            None => continue,
+                        }
+                    };
+                    if idn_bit_len(attribute).is_none() {
+                        continue;
+                    }
+                    mapped.push(MappedIdn {
+                        idn,
+                        attribute,
+                        name: self.idn_name(slave, drive_no, idn),
+                    });
+                }
+                telegrams.push((drive_no, i, mapped));
+            }
+        }
+        let (res, bit_lens) = soe_mapping(telegrams);
+        if bit_lens != [output_bits as usize, input_bits as usize] {
+            log::warn!(
+                "{:?}: SoE mapping ({:?} bits) differs from the process data size",
+                slave,
+                bit_lens
+            );
+        }
+        if res.is_empty() {
+            return None;
+        }
+        Some(res)
+    }
+
+    /// The IDNs of a list (e.g. the MDT configuration).
+    fn read_idn_list(&mut self, slave: ec::SlavePos, drive_no: u8, idn: u16) -> Option<Vec<u16>> {
+        let mut buf = [0; 4 + MAX_MAPPING * 2];
+        let data = self
+            .read_idn(
+                slave,
+                drive_no,
+                IdnElements::VALUE,
+                idn,
+                &mut buf,
+                SOE_TIMEOUT,
+            )
+            .ok()?;
+        idn_list(data)
+    }
+
+    fn idn_attribute(&mut self, slave: ec::SlavePos, drive_no: u8, idn: u16) -> Option<u32> {
+        let mut buf = [0; 4];
+        let data = self
+            .read_idn(
+                slave,
+                drive_no,
+                IdnElements::ATTRIBUTE,
+                idn,
+                &mut buf,
+                SOE_TIMEOUT,
+            )
+            .ok()?;
+        let data = <[u8; 4]>::try_from(&*data).ok()?;
+        Some(u32::from_le_bytes(data))
+    }
+
+    fn idn_name(&mut self, slave: ec::SlavePos, drive_no: u8, idn: u16) -> String {
+        let mut buf = [0; 64];
+        match self.read_idn(
+            slave,
+            drive_no,
+            IdnElements::NAME,
+            idn,
+            &mut buf,
+            SOE_TIMEOUT,
+        ) {
+            Ok(data) if data.len() > 4 => String::from_utf8_lossy(&data[4..])
+                .trim_end_matches('\0')
+                .to_string(),
+            _ => idn_label(idn),
+        }
+    }
+}
+
+/// Index of the PDO of a telegram of a drive.
+///
+/// The drive number is stored in bits 12-14
+/// (the parameter set of an IDN), so the PDOs
+/// of all drives of a slave have different indexes.
+const fn drive_pdo_idx(config: u16, drive_no: u8) -> u16 {
+    config | ((drive_no as u16 & 0x07) << 12)
+}
+
+/// The PDOs of the telegrams (drive number, index in [`TELEGRAMS`], mapped IDNs)
+/// and the bit length of the outputs and the inputs.
+fn soe_mapping(telegrams: Vec<(u8, usize, Vec<MappedIdn>)>) -> (PdoInfo, [usize; 2]) {
+    let mut res = vec![];
+    let mut entry_pos = 0;
+    let mut bit_offsets = [0_usize; 2];
+    for (drive_no, i, idns) in telegrams {
+        let telegram = &TELEGRAMS[i];
+        let pdo_idx = drive_pdo_idx(telegram.config, drive_no);
+        let mut entries = vec![];
+        for MappedIdn {
+            idn,
+            attribute,
+            name,
+        } in idns
+        {
+            let bit_len = match idn_bit_len(attribute) {
+                Some(bit_len) => bit_len,
+                None => continue,
+            };
+            let bit_offset = &mut bit_offsets[i];
+            entries.push(PdoEntryInfo {
+                idx: ec::PdoEntryIdx::new(pdo_idx, entries.len() as u8 + 1),
+                pos: ec::PdoEntryPos::new(entry_pos),
+                data_type: idn_data_type(attribute),
+                offset: ec::Offset {
+                    byte: *bit_offset / 8,
+                    bit: (*bit_offset % 8) as u32,
+                },
+                bit_len,
+                name,
+                sm: telegram.sm_type,
+                sdo: ec::SdoIdx::new(idn, 0),
+            });
+            *bit_offset += bit_len;
+            entry_pos += 1;
+        }
+        let info = ec::PdoInfo {
+            sm: ec::SmIdx::new(telegram.sm),
+            pos: ec::PdoPos::new(res.len() as u8),
+            idx: ec::Idx::new(pdo_idx),
+            entry_count: entries.len() as u8,
+            name: format!("Drive {} {}", drive_no, telegram.name),
+        };
+        res.push((info, entries));
+    }
+    (res, bit_offsets)
+}
+
+/// Attribute of a 16 bit unsigned value
+const IDN_ATTRIBUTE_U16: u32 = 0x0011_0000;
+
+/// Lists start with their current and maximum length in bytes.
+fn idn_list(data: &[u8]) -> Option<Vec<u16>> {
+    if data.len() < 4 {
+        return None;
+    }
+    let len = u16::from_le_bytes([data[0], data[1]]) as usize;
+    let idns = data[4..].get(..len.min(MAX_MAPPING * 2))?;
+    Some(
+        idns.chunks_exact(2)
+            .map(|idn| u16::from_le_bytes([idn[0], idn[1]]))
+            .collect(),
+    )
+}
+
+/// The bit length of an IDN (`None` for lists).
+const fn idn_bit_len(attribute: u32) -> Option<usize> {
+    if attribute & (1 << 18) != 0 {
+        return None;
+    }
+    Some(8 << ((attribute >> 16) & 0x03))
+}
+
+fn idn_data_type(attribute: u32) -> ec::DataType {
+    let len = (attribute >> 16) & 0x03;
+    match ((attribute >> 20) & 0x07, len) {
+        (0 | 1 | 3, 0) => ec::DataType::U8,
+        (0 | 1 | 3, 1) => ec::DataType::U16,
+        (0 | 1 | 3, 2) => ec::DataType::U32,
+        (0 | 1 | 3, 3) => ec::DataType::U64,
+        (2, 0) => ec::DataType::I8,
+        (2, 1) => ec::DataType::I16,
+        (2, 2) => ec::DataType::I32,
+        (2, 3) => ec::DataType::I64,
+        (6, 2) => ec::DataType::F32,
+        (6, 3) => ec::DataType::F64,
+        _ => ec::DataType::Raw,
+    }
+}
+
+/// Label of an IDN, e.g. `S-0-0047` or `P-0-0100`.
+fn idn_label(idn: u16) -> String {
+    let kind = if idn & 0x8000 == 0 { 'S' } else { 'P' };
+    format!("{}-{}-{:04}", kind, (idn >> 12) & 0x07, idn & 0x0FFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_idn_attribute() {
+        // signed 32 bit position
+        assert_eq!(idn_bit_len(0x0022_0001), Some(32));
+        assert_eq!(idn_data_type(0x0022_0001), ec::DataType::I32);
+        assert_eq!(idn_bit_len(IDN_ATTRIBUTE_U16), Some(16));
+        assert_eq!(idn_data_type(IDN_ATTRIBUTE_U16), ec::DataType::U16);
+        // list of IDNs
+        assert_eq!(idn_bit_len(0x0055_0001), None);
+    }
+
+    #[test]
+    fn parse_idn_list() {
+        let data = [4, 0, 8, 0, 47, 0, 36, 0];
+        assert_eq!(idn_list(&data), Some(vec![47, 36]));
+        assert_eq!(idn_list(&data[..2]), None);
+        assert_eq!(idn_label(47), "S-0-0047");
+        assert_eq!(idn_label(0x8064), "P-0-0100");
+    }
+
+    #[test]
+    fn map_the_telegrams_of_two_drives() {
+        let idns = |idn| {
+            vec![
+                MappedIdn {
+                    idn: IDN_CONTROL_WORD,
+                    attribute: IDN_ATTRIBUTE_U16,
+                    name: String::new(),
+                },
+                MappedIdn {
+                    idn,
+                    attribute: 0x0022_0001,
+                    name: String::new(),
+                },
+            ]
+        };
+        let (pdos, bit_lens) = soe_mapping(vec![(0, 0, idns(47)), (1, 0, idns(36))]);
+        assert_eq!(bit_lens, [96, 0]);
+        assert_eq!(pdos[0].0.idx, ec::Idx::new(24));
+        assert_eq!(pdos[1].0.idx, ec::Idx::new(0x1018));
+        assert_eq!(pdos[1].0.name, "Drive 1 MDT");
+
+        let mut outputs = [0; 12];
+        let idx = ec::PdoEntryIdx::new(drive_pdo_idx(IDN_MDT_CONFIG, 1), 2);
+        crate::pdo::set_pdo_value(&pdos, &mut outputs, idx, ec::Value::I32(-2)).unwrap();
+        assert_eq!(outputs, [0, 0, 0, 0, 0, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF]);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    config::{self, SlaveSelector},
+    ctx, ec, error, util, Master, SdoAbortCode, DEFAULT_SDO_TIMEOUT,
+};
+use std::collections::HashMap;
+
+/// Startup SDO that could not be applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupSdoError {
+    pub slave: ec::SlavePos,
+    pub idx: ec::SdoIdx,
+    pub value: ec::Value,
+    pub kind: StartupSdoErrorKind,
+}
+
+/// The `errors` are reported by SOEM.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartupSdoErrorKind {
+    /// The value could not be written.
+    Write {
+        abort_code: Option<SdoAbortCode>,
+        errors: Vec<ctx::Error>,
+    },
+    /// The value could not be read back.
+    Read {
+        abort_code: Option<SdoAbortCode>,
+        errors: Vec<ctx::Error>,
+    },
+    /// The value that was read back differs.
+    Mismatch { written: Vec<u8>, read: Vec<u8> },
+}
+
+/// SDO that is written in PRE-OP
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupSdo {
+    pub idx: ec::SdoIdx,
+    pub value: ec::Value,
+    /// Read the value back and compare it with the written one.
+    ///
+    /// Disable it for objects that can't be read back,
+    /// e.g. write-only commands.
+    pub verify: bool,
+}
+
+impl StartupSdo {
+    /// An SDO that is verified after writing it.
+    #[must_use]
+    pub const fn new(idx: ec::SdoIdx, value: ec::Value) -> Self {
+        Self {
+            idx,
+            value,
+            verify: true,
+        }
+    }
+}
+
+impl Master {
+    /// Add SDOs that are written in PRE-OP before the process data is mapped.
+    ///
+    /// Each slave gets the list of the most specific selector:
+    /// a position or alias overrides an identity
+    /// and an identity with revision overrides one without.
+    ///
+    /// The SDOs are written in the transition PRE-OP -> SAFE-OP
+    /// (before the PDO configuration), so they are applied again
+    /// if the slave is reconfigured by [`Master::supervise`].
+    pub fn add_startup_sdos(&mut self, selector: SlaveSelector, sdos: Vec<StartupSdo>) {
+        self.startup_sdos.push((selector, sdos));
+    }
+
+    /// The startup SDOs of each slave (by slave number).
+    pub(crate) fn slave_startup_sdos(&self) -> HashMap<u16, Vec<StartupSdo>> {
+        self.slaves()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| {
+                let sdos = config::select(&self.startup_sdos, ec::SlavePos::new(i as u16), s)?;
+                Some((i as u16 + 1, sdos.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Write and verify the startup SDOs of a slave.
+///
+/// `slave` is the SOEM slave number (starting with `1`).
+pub(crate) fn write_startup_sdos(
+    ctx: &mut ctx::HookCtx<'_>,
+    slave: u16,
+    sdos: &[StartupSdo],
+) -> Vec<StartupSdoError> {
+    let pos = ec::SlavePos::new(slave - 1);
+    log::debug!("Write {} startup SDOs to {:?}", sdos.len(), pos);
+    let mut errors = vec![];
+    for sdo in sdos {
+        if let Err(kind) = write_startup_sdo(ctx, slave, sdo) {
+            log::warn!(
+                "Could not apply startup SDO {:?} of {:?}: {:?}",
+                sdo.idx,
+                pos,
+                kind
+            );
+            errors.push(StartupSdoError {
+                slave: pos,
+                idx: sdo.idx,
+                value: sdo.value.clone(),
+                kind,
+            });
+        }
+    }
+    errors
+}
+
+fn write_startup_sdo(
+    ctx: &mut ctx::HookCtx<'_>,
+    slave: u16,
+    sdo: &StartupSdo,
+) -> std::result::Result<(), StartupSdoErrorKind> {
+    let index = u16::from(sdo.idx.idx);
+    let subindex = u8::from(sdo.idx.sub_idx);
+    let written =
+        util::value_to_bytes(sdo.value.clone()).map_err(|_| StartupSdoErrorKind::Write {
+            abort_code: None,
+            errors: vec![],
+        })?;
+
+    let wkc = ctx.sdo_write(slave, index, subindex, false, &written, DEFAULT_SDO_TIMEOUT);
+    if wkc <= 0 {
+        let errors = ctx_errors(ctx);
+        return Err(StartupSdoErrorKind::Write {
+            abort_code: error::sdo_abort_code(&errors),
+            errors,
+        });
+    }
+    if !sdo.verify {
+        return Ok(());
+    }
+
+    let mut target = vec![0; written.len()];
+    let (wkc, read) = ctx.sdo_read(
+        slave,
+        index,
+        subindex,
+        false,
+        &mut target,
+        DEFAULT_SDO_TIMEOUT,
+    );
+    if wkc <= 0 {
+        let errors = ctx_errors(ctx);
+        return Err(StartupSdoErrorKind::Read {
+            abort_code: error::sdo_abort_code(&errors),
+            errors,
+        });
+    }
+    if read != written.as_slice() {
+        return Err(StartupSdoErrorKind::Mismatch {
+            read: read.to_vec(),
+            written,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn ctx_errors(ctx: &mut ctx::HookCtx<'_>) -> Vec<ctx::Error> {
+    let errors: Vec<_> = std::iter::from_fn(|| ctx.pop_error()).collect();
+    log::debug!("Context errors: {:?}", errors);
+    errors
+}
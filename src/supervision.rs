@@ -0,0 +1,606 @@
+use crate::{ctx, ec, AlStatus, Error, Event, Master, Result, EC_NOFRAME};
+use std::{convert::TryFrom, time::Duration};
+
+const EC_STATE_NONE: u16 = 0x00;
+const EC_STATE_MASK: u16 = 0x0F;
+const EC_STATE_ERROR: u16 = 0x10;
+const EC_STATE_ACK: u16 = 0x10;
+
+const STATE_RECHECK_TIMEOUT: Duration = Duration::from_micros(2_000);
+const DEFAULT_RECOVERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Events that have not been fetched yet.
+/// If the queue is full, the oldest event is dropped.
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// Working counter supervision of a group
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct WkcCheck {
+    pub expected: Option<usize>,
+    pub mismatch: bool,
+}
+
+impl WkcCheck {
+    /// Compare the working counter with the expected one.
+    ///
+    /// It returns an event if the result differs from the last check.
+    pub(crate) fn update(&mut self, group: u8, wkc: usize) -> Option<Event> {
+        let expected = self.expected?;
+        let mismatch = wkc < expected;
+        if mismatch == self.mismatch {
+            return None;
+        }
+        self.mismatch = mismatch;
+        let event = if mismatch {
+            log::warn!(
+                "Group {}: working counter {} is below expected {}",
+                group,
+                wkc,
+                expected
+            );
+            Event::WkcMismatch {
+                group,
+                expected,
+                actual: wkc,
+            }
+        } else {
+            log::info!("Group {}: working counter is OK again", group);
+            Event::WkcRestored { group }
+        };
+        Some(event)
+    }
+}
+
+/// What was reported about a slave, to report only changes
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SlaveReport {
+    /// State of the last [`Event::SlaveNotOp`] (until the slave is in OP again)
+    not_op: Option<u16>,
+    /// A failed recovery was reported and no recovery succeeded since then.
+    recovery_failed: bool,
+}
+
+/// Settings for [`Master::supervise`]
+#[derive(Debug, Clone)]
+pub struct RecoveryConfig {
+    /// Timeout to recover or reconfigure a single slave
+    pub timeout: Duration,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_RECOVERY_TIMEOUT,
+        }
+    }
+}
+
+impl Master {
+    /// The working counter that is expected after
+    /// exchanging the process data of a group.
+    #[must_use]
+    pub fn expected_wkc(&self, group: u8) -> Option<usize> {
+        self.wkc.get(group as usize).and_then(|c| c.expected)
+    }
+
+    pub(crate) fn check_wkc(&mut self, group: u8, wkc: usize) {
+        if let Some(event) = self
+            .wkc
+            .get_mut(group as usize)
+            .and_then(|check| check.update(group, wkc))
+        {
+            self.push_event(event);
+        }
+    }
+
+    pub(crate) fn push_event(&mut self, event: Event) {
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            log::warn!("Event queue is full: drop the oldest event");
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Take over the working counter events of the split process data paths.
+    pub(crate) fn receive_cyclic_events(&mut self) {
+        let events: Vec<_> = match &self.cyclic_events {
+            Some(rx) => rx.try_iter().collect(),
+            None => return,
+        };
+        for event in events {
+            let (group, mismatch) = match event {
+                Event::WkcMismatch { group, .. } => (group, true),
+                Event::WkcRestored { group } => (group, false),
+                _ => continue,
+            };
+            if let Some(check) = self.wkc.get_mut(group as usize) {
+                check.mismatch = mismatch;
+            }
+            self.push_event(event);
+        }
+    }
+
+    /// Check the slave states of all groups with a working counter mismatch.
+    ///
+    /// Like `ecatcheck` of SOEM's `simple_test.c` this reads the states
+    /// and reports each slave that is not operational anymore
+    /// as an [`Event`]. Groups without mismatch are skipped,
+    /// so it's cheap to call this periodically.
+    /// The state of a slave is only reported when it changes.
+    pub fn check_slaves(&mut self) -> Result<()> {
+        self.check_groups(None)
+    }
+
+    /// Check the slave states like [`Master::check_slaves`]
+    /// and try to bring faulty slaves back to OP.
+    ///
+    /// Depending on its state a slave gets its error acknowledged,
+    /// is requested to go to OP or is reconfigured, which re-applies
+    /// its SM, FMMU and PDO configuration.
+    /// A lost slave that responds again only gets its configured
+    /// address restored. It's reconfigured and switched to OP
+    /// by the following calls.
+    /// Each step is reported as an [`Event`];
+    /// a failing recovery is only reported once until it succeeds.
+    ///
+    /// Like [`Master::request_states`] it doesn't request OP
+    /// if the slaves don't match the expected topology.
+    pub fn supervise(&mut self, cfg: &RecoveryConfig) -> Result<()> {
+        self.check_groups(Some(cfg))
+    }
+
+    fn check_groups(&mut self, recovery: Option<&RecoveryConfig>) -> Result<()> {
+        self.receive_cyclic_events();
+        let groups: Vec<u8> = (0..self.max_group() as u8)
+            .filter(|g| {
+                self.wkc
+                    .get(*g as usize)
+                    .map(|c| c.mismatch)
+                    .unwrap_or(false)
+                    || self.ctx.groups()[*g as usize].check_state()
+            })
+            .collect();
+        if groups.is_empty() {
+            return Ok(());
+        }
+        if self.ctx.read_state() == EC_NOFRAME {
+            return Err(Error::NoFrame);
+        }
+        for group in groups {
+            self.ctx.groups_mut()[group as usize].set_check_state(false);
+            for i in 1..=self.ctx.slave_count() as u16 {
+                if self.ctx.slaves()[i as usize].group() != group {
+                    continue;
+                }
+                let report = self.slave_reports.entry(i).or_default();
+                let mut supervisor = Supervisor {
+                    bus: &mut *self.ctx,
+                    recovery,
+                    op_allowed: self.topology_mismatches.is_empty(),
+                    events: vec![],
+                };
+                let op = supervisor.check_slave(i, report);
+                for event in supervisor.events {
+                    self.push_event(event);
+                }
+                if !op {
+                    self.ctx.groups_mut()[group as usize].set_check_state(true);
+                }
+            }
+            if !self.ctx.groups()[group as usize].check_state() {
+                log::info!("Group {}: all slaves are operational", group);
+            }
+        }
+        // The config hook logs the errors of reconfigured slaves;
+        // only `config_map` reports them.
+        let (startup_sdos, pdo_configs) = self.take_config_errors();
+        self.record_config_emergencies(&startup_sdos, &pdo_configs);
+        Ok(())
+    }
+}
+
+/// Access to the slaves for the supervision
+trait SlaveBus {
+    fn state(&self, i: u16) -> u16;
+    fn al_status(&self, i: u16) -> AlStatus;
+    fn is_lost(&self, i: u16) -> bool;
+    fn set_lost(&mut self, i: u16, lost: bool);
+    /// Wait until the slave reaches `state` and refresh its state.
+    fn state_check(&mut self, i: u16, state: u16, timeout: Duration);
+    fn write_state(&mut self, i: u16, state: u16);
+    /// Returns > 0 if successful.
+    fn recover(&mut self, i: u16, timeout: Duration) -> i32;
+    /// Returns the state of the slave.
+    fn reconfig(&mut self, i: u16, timeout: Duration) -> i32;
+}
+
+impl SlaveBus for ctx::Ctx {
+    fn state(&self, i: u16) -> u16 {
+        self.slaves()[i as usize].state()
+    }
+    fn al_status(&self, i: u16) -> AlStatus {
+        AlStatus::from(self.slaves()[i as usize].al_status_code())
+    }
+    fn is_lost(&self, i: u16) -> bool {
+        self.slaves()[i as usize].is_lost()
+    }
+    fn set_lost(&mut self, i: u16, lost: bool) {
+        self.slaves_mut()[i as usize].set_lost(lost);
+    }
+    fn state_check(&mut self, i: u16, state: u16, timeout: Duration) {
+        ctx::Ctx::state_check(self, i, state, timeout);
+    }
+    fn write_state(&mut self, i: u16, state: u16) {
+        self.slaves_mut()[i as usize].set_state(state);
+        if ctx::Ctx::write_state(self, i) == EC_NOFRAME {
+            log::warn!("Could not write state of slave {}: no frame", i);
+        }
+    }
+    fn recover(&mut self, i: u16, timeout: Duration) -> i32 {
+        self.recover_slave(i, timeout)
+    }
+    fn reconfig(&mut self, i: u16, timeout: Duration) -> i32 {
+        self.reconfig_slave(i, timeout)
+    }
+}
+
+/// State machine of the supervision of a single slave
+struct Supervisor<'a, B> {
+    bus: &'a mut B,
+    recovery: Option<&'a RecoveryConfig>,
+    /// `false` if the slaves don't match the expected topology
+    op_allowed: bool,
+    events: Vec<Event>,
+}
+
+impl<B: SlaveBus> Supervisor<'_, B> {
+    /// Check the state of a slave and try to recover it.
+    ///
+    /// It returns `true` if the slave is operational.
+    fn check_slave(&mut self, i: u16, report: &mut SlaveReport) -> bool {
+        let op = u8::from(ec::AlState::Op) as u16;
+        let slave = ec::SlavePos::from(i - 1);
+        let state = self.bus.state(i);
+
+        if state == op {
+            *report = SlaveReport::default();
+            return true;
+        }
+        if state == EC_STATE_NONE {
+            if !self.bus.is_lost(i) {
+                self.bus.state_check(i, op, STATE_RECHECK_TIMEOUT);
+                if self.bus.state(i) == EC_STATE_NONE {
+                    log::warn!("{:?} does not respond", slave);
+                    self.bus.set_lost(i, true);
+                    self.events.push(Event::SlaveLost { slave });
+                }
+            }
+        } else {
+            self.report_state(i, state, report);
+            if let Some(cfg) = self.recovery {
+                self.restore_op(i, state, cfg, report);
+            }
+        }
+
+        if self.bus.is_lost(i) {
+            if self.bus.state(i) == EC_STATE_NONE {
+                if let Some(cfg) = self.recovery {
+                    if self.bus.recover(i, cfg.timeout) > 0 {
+                        log::info!("{:?} recovered", slave);
+                        self.bus.set_lost(i, false);
+                        report.recovery_failed = false;
+                        self.events.push(Event::SlaveRecovered { slave });
+                    } else {
+                        log::debug!("Could not recover {:?}", slave);
+                        self.recovery_failed(slave, report);
+                    }
+                }
+            } else {
+                log::info!("{:?} found", slave);
+                self.bus.set_lost(i, false);
+                self.events.push(Event::SlaveFound { slave });
+            }
+        }
+        false
+    }
+
+    /// Report a slave that is not in OP, unless its state was reported before.
+    fn report_state(&mut self, i: u16, state: u16, report: &mut SlaveReport) {
+        if report.not_op == Some(state) {
+            return;
+        }
+        report.not_op = Some(state);
+        let slave = ec::SlavePos::from(i - 1);
+        let al_status = self.bus.al_status(i);
+        match ec::AlState::try_from((state & EC_STATE_MASK) as u8) {
+            Ok(al_state) => {
+                let error = state & EC_STATE_ERROR != 0;
+                log::warn!(
+                    "{:?} is in state {:?} (error: {}, AL status: {})",
+                    slave,
+                    al_state,
+                    error,
+                    al_status
+                );
+                self.events.push(Event::SlaveNotOp {
+                    slave,
+                    state: al_state,
+                    error,
+                    al_status,
+                });
+            }
+            Err(_) => {
+                log::warn!("{:?} has an invalid state 0x{:X}", slave, state);
+            }
+        }
+    }
+
+    fn restore_op(&mut self, i: u16, state: u16, cfg: &RecoveryConfig, report: &mut SlaveReport) {
+        let safe_op = u8::from(ec::AlState::SafeOp) as u16;
+        let slave = ec::SlavePos::from(i - 1);
+
+        if state == safe_op + EC_STATE_ERROR {
+            log::debug!("{:?} is in SAFE-OP + ERROR, acknowledge the error", slave);
+            self.bus.write_state(i, safe_op + EC_STATE_ACK);
+            self.events.push(Event::SlaveErrorAck { slave });
+        } else if state == safe_op {
+            self.request_op(i);
+        } else {
+            let res = self.bus.reconfig(i, cfg.timeout);
+            if res > 0 {
+                log::info!("{:?} reconfigured", slave);
+                self.bus.set_lost(i, false);
+                report.recovery_failed = false;
+                self.events.push(Event::SlaveReconfigured { slave });
+                if res as u16 == safe_op {
+                    self.request_op(i);
+                }
+            } else {
+                log::debug!("Could not reconfigure {:?}", slave);
+                self.recovery_failed(slave, report);
+            }
+        }
+    }
+
+    fn recovery_failed(&mut self, slave: ec::SlavePos, report: &mut SlaveReport) {
+        if !report.recovery_failed {
+            report.recovery_failed = true;
+            self.events.push(Event::RecoveryFailed { slave });
+        }
+    }
+
+    fn request_op(&mut self, i: u16) {
+        let slave = ec::SlavePos::from(i - 1);
+        if !self.op_allowed {
+            log::warn!(
+                "Refuse to request OP for {:?}: the slaves do not match the expected topology",
+                slave
+            );
+            return;
+        }
+        log::debug!("Request OP for {:?}", slave);
+        self.bus.write_state(i, u8::from(ec::AlState::Op) as u16);
+        self.events.push(Event::SlaveOpRequested { slave });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    const INIT: u16 = 0x01;
+    const PRE_OP: u16 = 0x02;
+    const SAFE_OP: u16 = 0x04;
+    const OP: u16 = 0x08;
+
+    /// A single slave that runs through a sequence of states
+    #[derive(Default)]
+    struct FakeSlave {
+        /// The state that is read by each check
+        states: VecDeque<u16>,
+        state: u16,
+        lost: bool,
+        recover_results: VecDeque<i32>,
+        reconfig_results: VecDeque<i32>,
+        written: Vec<u16>,
+    }
+
+    impl SlaveBus for FakeSlave {
+        fn state(&self, _: u16) -> u16 {
+            self.state
+        }
+        fn al_status(&self, _: u16) -> AlStatus {
+            AlStatus::NoError
+        }
+        fn is_lost(&self, _: u16) -> bool {
+            self.lost
+        }
+        fn set_lost(&mut self, _: u16, lost: bool) {
+            self.lost = lost;
+        }
+        fn state_check(&mut self, _: u16, _: u16, _: Duration) {}
+        fn write_state(&mut self, _: u16, state: u16) {
+            self.written.push(state);
+        }
+        fn recover(&mut self, _: u16, _: Duration) -> i32 {
+            self.recover_results.pop_front().unwrap()
+        }
+        fn reconfig(&mut self, _: u16, _: Duration) -> i32 {
+            self.reconfig_results.pop_front().unwrap()
+        }
+    }
+
+    /// Supervise the slave once per state of the sequence.
+    fn supervise(bus: &mut FakeSlave, op_allowed: bool) -> Vec<Vec<Event>> {
+        let cfg = RecoveryConfig::default();
+        let mut report = SlaveReport::default();
+        let mut res = vec![];
+        while let Some(state) = bus.states.pop_front() {
+            bus.state = state;
+            let mut supervisor = Supervisor {
+                bus: &mut *bus,
+                recovery: Some(&cfg),
+                op_allowed,
+                events: vec![],
+            };
+            let op = supervisor.check_slave(1, &mut report);
+            assert_eq!(op, state == OP);
+            res.push(supervisor.events);
+        }
+        res
+    }
+
+    fn not_op(state: ec::AlState, error: bool) -> Event {
+        Event::SlaveNotOp {
+            slave: ec::SlavePos::from(0),
+            state,
+            error,
+            al_status: AlStatus::NoError,
+        }
+    }
+
+    #[test]
+    fn report_each_state_once() {
+        let slave = ec::SlavePos::from(0);
+        let mut bus = FakeSlave {
+            states: vec![
+                SAFE_OP + EC_STATE_ERROR,
+                SAFE_OP + EC_STATE_ERROR,
+                SAFE_OP,
+                OP,
+                SAFE_OP,
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let events = supervise(&mut bus, true);
+        assert_eq!(
+            events,
+            vec![
+                vec![
+                    not_op(ec::AlState::SafeOp, true),
+                    Event::SlaveErrorAck { slave }
+                ],
+                vec![Event::SlaveErrorAck { slave }],
+                vec![
+                    not_op(ec::AlState::SafeOp, false),
+                    Event::SlaveOpRequested { slave }
+                ],
+                vec![],
+                vec![
+                    not_op(ec::AlState::SafeOp, false),
+                    Event::SlaveOpRequested { slave }
+                ],
+            ]
+        );
+        assert_eq!(
+            bus.written,
+            vec![SAFE_OP + EC_STATE_ACK, SAFE_OP + EC_STATE_ACK, OP, OP]
+        );
+    }
+
+    #[test]
+    fn refuse_op_on_a_topology_mismatch() {
+        let slave = ec::SlavePos::from(0);
+        let mut bus = FakeSlave {
+            states: vec![SAFE_OP, PRE_OP].into(),
+            reconfig_results: vec![SAFE_OP as i32].into(),
+            ..Default::default()
+        };
+        let events = supervise(&mut bus, false);
+        assert_eq!(
+            events,
+            vec![
+                vec![not_op(ec::AlState::SafeOp, false)],
+                vec![
+                    not_op(ec::AlState::PreOp, false),
+                    Event::SlaveReconfigured { slave }
+                ],
+            ]
+        );
+        assert!(bus.written.is_empty());
+    }
+
+    #[test]
+    fn report_a_failing_reconfiguration_once() {
+        let slave = ec::SlavePos::from(0);
+        let mut bus = FakeSlave {
+            states: vec![PRE_OP, PRE_OP, PRE_OP, PRE_OP].into(),
+            reconfig_results: vec![0, 0, SAFE_OP as i32, 0].into(),
+            ..Default::default()
+        };
+        let events = supervise(&mut bus, true);
+        assert_eq!(
+            events,
+            vec![
+                vec![
+                    not_op(ec::AlState::PreOp, false),
+                    Event::RecoveryFailed { slave }
+                ],
+                vec![],
+                vec![
+                    Event::SlaveReconfigured { slave },
+                    Event::SlaveOpRequested { slave }
+                ],
+                vec![Event::RecoveryFailed { slave }],
+            ]
+        );
+    }
+
+    #[test]
+    fn recover_a_lost_slave() {
+        let slave = ec::SlavePos::from(0);
+        let mut bus = FakeSlave {
+            states: vec![
+                EC_STATE_NONE,
+                EC_STATE_NONE,
+                EC_STATE_NONE,
+                INIT,
+                SAFE_OP,
+                OP,
+            ]
+            .into(),
+            recover_results: vec![0, 0, 1].into(),
+            reconfig_results: vec![SAFE_OP as i32].into(),
+            ..Default::default()
+        };
+        let events = supervise(&mut bus, true);
+        assert_eq!(
+            events,
+            vec![
+                vec![Event::SlaveLost { slave }, Event::RecoveryFailed { slave }],
+                vec![],
+                vec![Event::SlaveRecovered { slave }],
+                vec![
+                    not_op(ec::AlState::Init, false),
+                    Event::SlaveReconfigured { slave },
+                    Event::SlaveOpRequested { slave }
+                ],
+                vec![
+                    not_op(ec::AlState::SafeOp, false),
+                    Event::SlaveOpRequested { slave }
+                ],
+                vec![],
+            ]
+        );
+        assert!(!bus.lost);
+    }
+
+    #[test]
+    fn drop_the_oldest_event_if_the_queue_is_full() {
+        let mut master = Master::with_ctx(Box::new(crate::ctx::Ctx::default()));
+        for i in 0..=MAX_QUEUED_EVENTS {
+            master.push_event(Event::SlaveLost {
+                slave: ec::SlavePos::from(i as u16),
+            });
+        }
+        assert_eq!(master.events.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(
+            master.pop_event(),
+            Some(Event::SlaveLost {
+                slave: ec::SlavePos::from(1)
+            })
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{ec, Error, Master, Result, SlaveIdentity};
+use std::ops::RangeInclusive;
+
+/// Slave that is expected at a certain position of the bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedSlave {
+    pub vendor_id: u32,
+    pub product_code: u32,
+    /// If `None`, all revisions are accepted.
+    pub revision: Option<RangeInclusive<u32>>,
+    /// If `None`, the alias address is not checked.
+    pub alias: Option<u16>,
+}
+
+impl ExpectedSlave {
+    fn matches(&self, id: &SlaveIdentity) -> bool {
+        let revision_matches = match (&self.revision, id.revision) {
+            (Some(range), Some(rev)) => range.contains(&rev),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        self.vendor_id == id.vendor_id && self.product_code == id.product_code && revision_matches
+    }
+}
+
+/// Difference between the expected and the actual bus layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopologyMismatch {
+    /// The slave was not found.
+    Missing {
+        slave: ec::SlavePos,
+        expected: ExpectedSlave,
+    },
+    /// A slave was found that is not expected.
+    Unexpected {
+        slave: ec::SlavePos,
+        found: SlaveIdentity,
+    },
+    /// Another slave type (or revision) was found.
+    Identity {
+        slave: ec::SlavePos,
+        expected: ExpectedSlave,
+        found: SlaveIdentity,
+    },
+    /// The slave has another alias address.
+    Alias {
+        slave: ec::SlavePos,
+        expected: u16,
+        found: u16,
+    },
+}
+
+/// Compare the expected slaves with the found ones
+/// (identity and alias address), both ordered by position.
+pub(crate) fn check_topology(
+    expected: &[ExpectedSlave],
+    found: &[(SlaveIdentity, u16)],
+) -> Vec<TopologyMismatch> {
+    let mut mismatches = vec![];
+    for i in 0..expected.len().max(found.len()) {
+        let slave = ec::SlavePos::new(i as u16);
+        match (expected.get(i), found.get(i)) {
+            (Some(expected), Some((id, alias))) => {
+                if !expected.matches(id) {
+                    mismatches.push(TopologyMismatch::Identity {
+                        slave,
+                        expected: expected.clone(),
+                        found: *id,
+                    });
+                    continue;
+                }
+                match expected.alias {
+                    Some(a) if a != *alias => {
+                        mismatches.push(TopologyMismatch::Alias {
+                            slave,
+                            expected: a,
+                            found: *alias,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            (Some(expected), None) => mismatches.push(TopologyMismatch::Missing {
+                slave,
+                expected: expected.clone(),
+            }),
+            (None, Some((id, _))) => {
+                mismatches.push(TopologyMismatch::Unexpected { slave, found: *id })
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    mismatches
+}
+
+impl Master {
+    /// Set the expected bus layout (ordered by position).
+    ///
+    /// If the found slaves differ, [`Master::auto_config`] fails with
+    /// [`Error::Topology`] and the slaves can't be switched to OP.
+    pub fn set_expected_topology(&mut self, slaves: Vec<ExpectedSlave>) {
+        self.expected_topology = Some(slaves);
+    }
+
+    /// Differences between the expected and the found slaves
+    /// of the last scan.
+    #[must_use]
+    pub fn topology_mismatches(&self) -> &[TopologyMismatch] {
+        &self.topology_mismatches
+    }
+
+    pub(crate) fn check_topology(&mut self) -> Result<()> {
+        let expected = match &self.expected_topology {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let found: Vec<_> = self
+            .slaves()
+            .iter()
+            .map(|s| (SlaveIdentity::of(s), s.alias_addr()))
+            .collect();
+        self.topology_mismatches = check_topology(expected, &found);
+        if self.topology_mismatches.is_empty() {
+            log::debug!("Found slaves match the expected topology");
+            return Ok(());
+        }
+        for m in &self.topology_mismatches {
+            log::warn!("Topology mismatch: {:?}", m);
+        }
+        Err(Error::Topology(self.topology_mismatches.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(product_code: u32, revision: u32) -> SlaveIdentity {
+        SlaveIdentity {
+            vendor_id: 2,
+            product_code,
+            revision: Some(revision),
+        }
+    }
+
+    fn expected(product_code: u32) -> ExpectedSlave {
+        ExpectedSlave {
+            vendor_id: 2,
+            product_code,
+            revision: None,
+            alias: None,
+        }
+    }
+
+    #[test]
+    fn matching_topology() {
+        let expected = vec![
+            expected(0x044c_2c52),
+            ExpectedSlave {
+                revision: Some(0x0010_0000..=0x0013_0000),
+                alias: Some(7),
+                ..expected(0x0bb8_3052)
+            },
+        ];
+        let found = vec![(id(0x044c_2c52, 1), 0), (id(0x0bb8_3052, 0x0012_0000), 7)];
+        assert!(check_topology(&expected, &found).is_empty());
+    }
+
+    #[test]
+    fn topology_mismatches() {
+        let expected = vec![
+            expected(1),
+            ExpectedSlave {
+                revision: Some(3..=4),
+                ..expected(2)
+            },
+            ExpectedSlave {
+                alias: Some(7),
+                ..expected(3)
+            },
+            expected(4),
+        ];
+        let found = vec![(id(9, 0), 0), (id(2, 5), 0), (id(3, 0), 8)];
+        let pos = ec::SlavePos::new;
+        assert_eq!(
+            check_topology(&expected, &found),
+            vec![
+                TopologyMismatch::Identity {
+                    slave: pos(0),
+                    expected: expected[0].clone(),
+                    found: id(9, 0),
+                },
+                TopologyMismatch::Identity {
+                    slave: pos(1),
+                    expected: expected[1].clone(),
+                    found: id(2, 5),
+                },
+                TopologyMismatch::Alias {
+                    slave: pos(2),
+                    expected: 7,
+                    found: 8,
+                },
+                TopologyMismatch::Missing {
+                    slave: pos(3),
+                    expected: expected[3].clone(),
+                },
+            ]
+        );
+        let found = vec![
+            (id(1, 0), 0),
+            (id(2, 3), 0),
+            (id(3, 0), 7),
+            (id(4, 0), 0),
+            (id(5, 0), 0),
+        ];
+        assert_eq!(
+            check_topology(&expected, &found),
+            vec![TopologyMismatch::Unexpected {
+                slave: pos(4),
+                found: id(5, 0),
+            }]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Helpers to read XML configuration files (ENI & ESI)
+
+use roxmltree::Node;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+/// Error while reading an XML configuration file
+#[derive(Debug, Error)]
+pub enum XmlError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error("Missing element <{0}>")]
+    MissingElement(&'static str),
+    #[error("Missing attribute {0:?}")]
+    MissingAttribute(&'static str),
+    #[error("Invalid value {value:?} of <{element}>")]
+    InvalidValue {
+        element: &'static str,
+        value: String,
+    },
+}
+
+pub(crate) type Result<T> = std::result::Result<T, XmlError>;
+
+pub(crate) fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+pub(crate) fn children<'a, 'i: 'a>(
+    node: Node<'a, 'i>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'i>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+pub(crate) fn text<'a>(node: Node<'a, '_>, name: &'static str) -> Result<&'a str> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .ok_or(XmlError::MissingElement(name))
+}
+
+pub(crate) fn value(node: Node<'_, '_>, name: &'static str) -> Result<u64> {
+    number(name, text(node, name)?)
+}
+
+pub(crate) fn i32_value(node: Node<'_, '_>, name: &'static str) -> Result<i32> {
+    let s = text(node, name)?;
+    i32::try_from(signed_number(name, s)?).map_err(|_| invalid(name, s))
+}
+
+pub(crate) fn u32_value(node: Node<'_, '_>, name: &'static str) -> Result<u32> {
+    let v = value(node, name)?;
+    u32::try_from(v).map_err(|_| invalid(name, &v.to_string()))
+}
+
+pub(crate) fn u16_value(node: Node<'_, '_>, name: &'static str) -> Result<u16> {
+    let v = value(node, name)?;
+    u16::try_from(v).map_err(|_| invalid(name, &v.to_string()))
+}
+
+pub(crate) fn u8_value(node: Node<'_, '_>, name: &'static str) -> Result<u8> {
+    let v = value(node, name)?;
+    u8::try_from(v).map_err(|_| invalid(name, &v.to_string()))
+}
+
+pub(crate) fn attr_u32(node: Node<'_, '_>, name: &'static str) -> Result<Option<u32>> {
+    node.attribute(name)
+        .map(|a| u32::try_from(number(name, a)?).map_err(|_| invalid(name, a)))
+        .transpose()
+}
+
+/// Numbers are either decimal or hexadecimal with a `#x` prefix.
+pub(crate) fn number(element: &'static str, s: &str) -> Result<u64> {
+    let s = s.trim();
+    let n = match s.strip_prefix("#x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    n.map_err(|_| invalid(element, s))
+}
+
+/// Signed numbers (e.g. a DC shift time) can have a `-` sign.
+pub(crate) fn signed_number(element: &'static str, s: &str) -> Result<i64> {
+    let s = s.trim();
+    let (negative, abs) = match s.strip_prefix('-') {
+        Some(abs) => (true, abs),
+        None => (false, s),
+    };
+    let n = i64::try_from(number(element, abs)?).map_err(|_| invalid(element, s))?;
+    Ok(if negative { -n } else { n })
+}
+
+pub(crate) fn flag(attr: Option<&str>) -> bool {
+    matches!(attr, Some("1") | Some("true"))
+}
+
+pub(crate) fn invalid(element: &'static str, value: &str) -> XmlError {
+    XmlError::InvalidValue {
+        element,
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_numbers() {
+        assert_eq!(number("x", "#x1A00").unwrap(), 0x1A00);
+        assert_eq!(number("x", " 42 ").unwrap(), 42);
+        assert!(number("x", "-1").is_err());
+        assert_eq!(signed_number("x", "-250000").unwrap(), -250_000);
+        assert_eq!(signed_number("x", "-#x10").unwrap(), -16);
+        assert_eq!(signed_number("x", "42").unwrap(), 42);
+        assert!(signed_number("x", "--1").is_err());
+    }
+}